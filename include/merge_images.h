@@ -0,0 +1,81 @@
+#ifndef MERGE_IMAGES_H
+#define MERGE_IMAGES_H
+
+/* 由 cbindgen 根据 src/ffi.rs 生成，不要手动修改 */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// 成功
+#define MERGE_IMAGES_OK 0
+
+// 参数不合法，如 inputs 为空指针
+#define MERGE_IMAGES_INVALID_ARGUMENT 1
+
+// 没有输入图片
+#define MERGE_IMAGES_NO_IMAGES 2
+
+// 某张图片解码失败或格式不支持
+#define MERGE_IMAGES_DECODE_ERROR 3
+
+// 图片处理失败
+#define MERGE_IMAGES_PROCESS_ERROR 4
+
+// 编码输出图片失败
+#define MERGE_IMAGES_ENCODE_ERROR 5
+
+// 其他错误
+#define MERGE_IMAGES_OTHER_ERROR 6
+
+// 内部错误（panic）
+#define MERGE_IMAGES_PANIC 7
+
+// 拼图结果，用完后必须调用 `merge_images_free` 释放
+typedef struct MergeImagesResult {
+  // `MERGE_IMAGES_OK` 或错误码
+  int32_t code;
+  // 成功时是编码后的图片，失败时为 NULL
+  uint8_t *data;
+  size_t len;
+  // 失败时是 NUL 结尾的 UTF-8 错误信息，成功时为 NULL
+  char *message;
+} MergeImagesResult;
+
+// 调用者持有的一段输入数据
+typedef struct MergeImagesBuffer {
+  const uint8_t *data;
+  size_t len;
+} MergeImagesBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// 宫格拼图，输出 JPEG，即 `merge_images::merge`
+//
+// # Safety
+// inputs 指向 count 个 `MergeImagesBuffer`，每个 data 指向 len 字节可读的内存；
+// 调用期间这些内存不能被修改或释放
+struct MergeImagesResult merge_images_grid(const struct MergeImagesBuffer *inputs, size_t count);
+
+// 瀑布流拼图，输出 JPEG，即 `merge_images::waterfall`
+//
+// # Safety
+// 与 `merge_images_grid` 相同
+struct MergeImagesResult merge_images_waterfall(const struct MergeImagesBuffer *inputs,
+                                                size_t count);
+
+// 释放结果中的图片和错误信息，并把它们置为 NULL；重复调用是安全的
+//
+// # Safety
+// result 为 NULL，或者指向由本库返回、未被修改过的 `MergeImagesResult`
+void merge_images_free(struct MergeImagesResult *result);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MERGE_IMAGES_H */
@@ -0,0 +1,55 @@
+from concurrent.futures import ThreadPoolExecutor
+from pathlib import Path
+
+import pytest
+
+import merge_images
+
+DATA = Path(__file__).resolve().parents[2] / "test-data"
+
+
+def data(name):
+    return (DATA / name).read_bytes()
+
+
+def images():
+    return [data(name) for name in ["1.png", "2.png", "3.png", "4.jpg"]]
+
+
+def test_merge():
+    output = merge_images.merge(images())
+    assert output[:2] == b"\xff\xd8"
+
+
+def test_waterfall_with_options():
+    options = merge_images.MergeOptions(format="png", padding=0, background=(0, 0, 0))
+    output = merge_images.waterfall(images(), options, fit="contain")
+    assert output[:8] == b"\x89PNG\r\n\x1a\n"
+    (Path("output-python.png")).write_bytes(output)
+
+
+def test_options_are_mutable():
+    options = merge_images.MergeOptions()
+    assert options.format == "jpeg"
+    options.format = "webp"
+    options.lossless = True
+    assert 'format="webp"' in repr(options)
+    output = merge_images.merge(images(), options)
+    assert output[:4] == b"RIFF" and output[8:12] == b"WEBP"
+
+
+def test_errors():
+    with pytest.raises(merge_images.MergeError):
+        merge_images.merge([b"not an image", data("1.png")])
+    with pytest.raises(ValueError):
+        merge_images.merge(images(), merge_images.MergeOptions(format="gif"))
+    with pytest.raises(ValueError):
+        merge_images.merge(images(), fit="fill")
+
+
+def test_threads():
+    # 拼图时释放了 GIL，多个线程的结果与单线程相同
+    expected = merge_images.merge(images())
+    with ThreadPoolExecutor(4) as pool:
+        outputs = list(pool.map(lambda _: merge_images.merge(images()), range(8)))
+    assert all(output == expected for output in outputs)
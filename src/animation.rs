@@ -0,0 +1,313 @@
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+use gif::{ColorOutput, DecodeOptions, DisposalMethod, Repeat};
+
+use crate::error::BackendError;
+use crate::prelude::*;
+
+/// 延迟为 0 或 1（单位 10ms）的 GIF 帧，浏览器都按 100ms 播放
+const DEFAULT_DELAY_MS: u32 = 100;
+/// 解码 GIF 时所有帧合计的最大字节数（RGBA），超出后不再继续解码
+const MAX_DECODED_BYTES: usize = 512 << 20;
+/// GIF 逻辑屏幕的最大像素数；屏幕大小来自文件头，在解码任何一帧之前就要分配
+const MAX_SCREEN_PIXELS: usize = 8000 * 8000;
+/// 输出 GIF 时量化颜色的速度，1~30，越大越快、颜色越差
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+/// 动图的输出格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    /// 需要启用 `webp` feature
+    #[cfg(feature = "webp")]
+    WebP {
+        /// 0~100
+        quality: f32,
+    },
+}
+
+impl AnimationFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => ".gif",
+            #[cfg(feature = "webp")]
+            AnimationFormat::WebP { .. } => ".webp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "image/gif",
+            #[cfg(feature = "webp")]
+            AnimationFormat::WebP { .. } => "image/webp",
+        }
+    }
+}
+
+/// 动图输出参数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    /// 输出的最大帧数，各输入的切换时刻超过这个数量时改为均匀采样
+    pub max_frames: usize,
+    /// 输出的最大时长（毫秒）；也是每张输入最多解码的时长
+    pub max_duration_ms: u32,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::Gif,
+            max_frames: 100,
+            max_duration_ms: 10_000,
+        }
+    }
+}
+
+/// GIF 解码出的帧，每一帧都已经按照 disposal method 合成为完整的画面
+pub(crate) struct RgbaFrames {
+    pub width: i32,
+    pub height: i32,
+    /// RGBA
+    pub frames: Vec<Vec<u8>>,
+    /// 每一帧的播放时长（毫秒）
+    pub delays: Vec<u32>,
+}
+
+/// 解码 GIF 的所有帧；累计时长达到 max_duration_ms 或者解码出的数据超过 `MAX_DECODED_BYTES`
+/// 后不再继续解码，max_duration_ms 为 0 时只解码第一帧
+pub(crate) fn decode_gif(bytes: &[u8], max_duration_ms: u32) -> Result<RgbaFrames, BackendError> {
+    let gif_error = |e: gif::DecodingError| BackendError::new(format!("gif: {}", e));
+    let mut options = DecodeOptions::new();
+    options.set_color_output(ColorOutput::RGBA);
+    let mut decoder = options.read_info(bytes).map_err(gif_error)?;
+    let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+    if width * height > MAX_SCREEN_PIXELS {
+        return Err(BackendError::new(format!(
+            "gif screen is too large: {}x{}",
+            width, height
+        )));
+    }
+
+    let mut screen = vec![0u8; width * height * 4];
+    let mut frames = vec![];
+    let mut delays = vec![];
+    let mut duration = 0;
+    while let Some(frame) = decoder.read_next_frame().map_err(gif_error)? {
+        if !frames.is_empty() && (frames.len() + 1) * screen.len() > MAX_DECODED_BYTES {
+            debug!(
+                "gif frames exceed {} bytes, stop decoding",
+                MAX_DECODED_BYTES
+            );
+            break;
+        }
+        let previous = match frame.dispose {
+            DisposalMethod::Previous => Some(screen.clone()),
+            _ => None,
+        };
+        // 帧可能超出逻辑屏幕，超出的部分丢弃
+        let left = (frame.left as usize).min(width);
+        let top = (frame.top as usize).min(height);
+        let right = (left + frame.width as usize).min(width);
+        let bottom = (top + frame.height as usize).min(height);
+        for y in top..bottom {
+            for x in left..right {
+                let src = ((y - top) * frame.width as usize + (x - left)) * 4;
+                let pixel = &frame.buffer[src..src + 4];
+                // 透明像素保留下面的内容
+                if pixel[3] != 0 {
+                    let dst = (y * width + x) * 4;
+                    screen[dst..dst + 4].copy_from_slice(pixel);
+                }
+            }
+        }
+        frames.push(screen.clone());
+
+        let delay = match frame.delay {
+            0 | 1 => DEFAULT_DELAY_MS,
+            delay => delay as u32 * 10,
+        };
+        delays.push(delay);
+        duration += delay;
+        if duration >= max_duration_ms {
+            debug!("gif longer than {} ms, stop decoding", max_duration_ms);
+            break;
+        }
+
+        match (frame.dispose, previous) {
+            (DisposalMethod::Background, _) => {
+                for y in top..bottom {
+                    screen[(y * width + left) * 4..(y * width + right) * 4]
+                        .iter_mut()
+                        .for_each(|v| *v = 0);
+                }
+            }
+            (DisposalMethod::Previous, Some(previous)) => screen = previous,
+            _ => {}
+        }
+    }
+    debug!("decoded {} frames from gif", frames.len());
+    Ok(RgbaFrames {
+        width: width as i32,
+        height: height as i32,
+        frames,
+        delays,
+    })
+}
+
+/// 画布上的一个动图格子，每一帧都已经缩放到格子大小
+pub(crate) struct AnimatedTile {
+    pub rect: Rect,
+    pub frames: Vec<Image>,
+    /// 每一帧的播放时长（毫秒）
+    pub delays: Vec<u32>,
+}
+
+impl AnimatedTile {
+    /// t 时刻（毫秒）显示的帧，播放完后从头循环
+    fn frame_at(&self, t: u32) -> &Image {
+        let duration: u32 = self.delays.iter().sum();
+        let mut t = t % duration.max(1);
+        for (frame, delay) in self.frames.iter().zip(&self.delays) {
+            if t < *delay {
+                return frame;
+            }
+            t -= delay;
+        }
+        &self.frames[self.frames.len() - 1]
+    }
+}
+
+/// 输出的时间轴：每一帧的 (开始时刻, 播放时长)，单位毫秒。
+///
+/// 总时长取最长的输入，但不超过 max_duration_ms，较短的输入循环播放；
+/// 输出帧在任一输入切换帧时切换，切换时刻太多时改为均匀采样。
+/// 时刻都取整到 10ms，这是 GIF 延迟的单位
+fn timeline(durations: &[Vec<u32>], options: &AnimationOptions) -> Vec<(u32, u32)> {
+    let total = durations
+        .iter()
+        .map(|delays| delays.iter().sum::<u32>())
+        .max()
+        .unwrap_or(0)
+        .min(options.max_duration_ms);
+    if total == 0 {
+        return vec![(0, DEFAULT_DELAY_MS)];
+    }
+    let max_frames = options.max_frames.max(1);
+
+    let mut starts = BTreeSet::new();
+    starts.insert(0);
+    'tiles: for delays in durations {
+        if delays.iter().sum::<u32>() == 0 {
+            continue;
+        }
+        let mut t = 0;
+        loop {
+            for delay in delays {
+                t += delay;
+                if t >= total || starts.len() > max_frames {
+                    continue 'tiles;
+                }
+                starts.insert(t / 10 * 10);
+            }
+        }
+    }
+
+    let starts: Vec<u32> = if starts.len() > max_frames {
+        let step = total.div_ceil(max_frames as u32);
+        let step = (step.div_ceil(10) * 10).max(10);
+        debug!("too many frames, sample every {} ms", step);
+        (0..).map(|k| k * step).take_while(|t| *t < total).collect()
+    } else {
+        starts.into_iter().collect()
+    };
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(total);
+            (start, end - start)
+        })
+        .collect()
+}
+
+fn encode_error(e: impl Display) -> MergeError {
+    MergeError::Encode(BackendError::new(e.to_string()))
+}
+
+/// 在画布上播放各个动图格子，编码为动图；canvas 上已经画好了其他格子
+pub(crate) fn encode(
+    canvas: &Image,
+    tiles: &[AnimatedTile],
+    options: &AnimationOptions,
+) -> Result<(Vec<u8>, usize)> {
+    let durations: Vec<Vec<u32>> = tiles.iter().map(|tile| tile.delays.clone()).collect();
+    let timeline = timeline(&durations, options);
+    info!(
+        "encoding {} frames, {} ms in total",
+        timeline.len(),
+        timeline.iter().map(|(_, delay)| delay).sum::<u32>()
+    );
+
+    let render = |t: u32| -> Result<Vec<u8>> {
+        let mut frame = Active::copy(canvas)?;
+        for tile in tiles {
+            Active::paste(tile.frame_at(t), &mut frame, tile.rect)?;
+        }
+        Ok(Active::to_rgb(&frame)?)
+    };
+
+    let (width, height) = Active::size(canvas);
+    let bytes = match options.format {
+        AnimationFormat::Gif => {
+            let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+                (Ok(width), Ok(height)) => (width, height),
+                _ => {
+                    return Err(encode_error(format!(
+                        "canvas {} x {} is too large for gif",
+                        width, height
+                    )))
+                }
+            };
+            let mut encoder =
+                gif::Encoder::new(Vec::new(), width, height, &[]).map_err(encode_error)?;
+            encoder.set_repeat(Repeat::Infinite).map_err(encode_error)?;
+            for &(start, delay) in &timeline {
+                let rgb = render(start)?;
+                let mut frame = gif::Frame::from_rgb_speed(width, height, &rgb, GIF_QUANTIZE_SPEED);
+                // 均匀采样时一帧可能超过 GIF 能表示的最长延迟
+                frame.delay =
+                    u16::try_from((delay.saturating_add(5) / 10).max(1)).unwrap_or(u16::MAX);
+                encoder.write_frame(&frame).map_err(encode_error)?;
+            }
+            encoder.into_inner().map_err(encode_error)?
+        }
+        #[cfg(feature = "webp")]
+        AnimationFormat::WebP { quality } => {
+            let frames = timeline
+                .iter()
+                .map(|&(start, _)| Ok((render(start)?, start)))
+                .collect::<Result<Vec<_>>>()?;
+            let mut config = webp::WebPConfig::new()
+                .map_err(|_| encode_error("failed to initialize webp config"))?;
+            config.quality = quality.clamp(0., 100.);
+            let mut encoder = webp::AnimEncoder::new(width as u32, height as u32, &config);
+            encoder.set_loop_count(0);
+            for (rgb, start) in &frames {
+                encoder.add_frame(webp::AnimFrame::from_rgb(
+                    rgb,
+                    width as u32,
+                    height as u32,
+                    *start as i32,
+                ));
+            }
+            encoder
+                .try_encode()
+                .map_err(|e| encode_error(format!("{:?}", e)))?
+                .to_vec()
+        }
+    };
+    Ok((bytes, timeline.len()))
+}
@@ -0,0 +1,66 @@
+//! 异步接口，需要启用 `tokio` feature。
+//!
+//! 解码、缩放和编码都很耗 CPU，这里把它们放到 tokio 的阻塞线程池中执行，不会阻塞调用者的 executor。
+//! 返回的 future 被丢弃（如请求被取消、超时）时，拼图在处理完当前的图片后停止。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::prelude::*;
+use crate::utils;
+use crate::{GridLayout, Layout, MergeOptions, MergeOutput, WaterfallLayout};
+
+/// future 被丢弃时通知阻塞线程池中的拼图停止
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 异步地用自定义的布局生成拼图，见 [`merge_with_layout`](crate::merge_with_layout)
+pub async fn merge_with_layout_async<L: Layout + Send + 'static>(
+    image_bytes: Vec<Bytes>,
+    layout: L,
+    options: MergeOptions,
+) -> Result<MergeOutput> {
+    merge_holding(image_bytes, layout, options, ()).await
+}
+
+/// 同 `merge_with_layout_async`；hold 随拼图移动到阻塞线程池，拼图真正结束后才被丢弃，
+/// 即使 future 已经被丢弃。用来持有并发许可等资源
+pub(crate) async fn merge_holding<L: Layout + Send + 'static, H: Send + 'static>(
+    image_bytes: Vec<Bytes>,
+    layout: L,
+    options: MergeOptions,
+    hold: H,
+) -> Result<MergeOutput> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _guard = CancelOnDrop(cancelled.clone());
+    let task = tokio::task::spawn_blocking(move || {
+        let _hold = hold;
+        utils::merge_cancellable(&image_bytes, &layout, &options, &cancelled)
+    });
+    match task.await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        // 运行时正在关闭
+        Err(_) => Err(MergeError::Cancelled),
+    }
+}
+
+/// 异步的宫格拼图，见 [`merge_with`](crate::merge_with)
+pub async fn merge_async(image_bytes: Vec<Bytes>, options: MergeOptions) -> Result<MergeOutput> {
+    merge_with_layout_async(image_bytes, GridLayout::default(), options).await
+}
+
+/// 异步的瀑布流拼图，见 [`waterfall_with`](crate::waterfall_with)
+pub async fn waterfall_async(
+    image_bytes: Vec<Bytes>,
+    options: MergeOptions,
+) -> Result<MergeOutput> {
+    merge_with_layout_async(image_bytes, WaterfallLayout::default(), options).await
+}
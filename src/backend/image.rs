@@ -0,0 +1,340 @@
+use std::convert::TryFrom;
+use std::io::Cursor;
+
+use image::codecs::webp::WebPEncoder;
+use image::codecs::{bmp, png, tiff};
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage, Rgba, RgbaImage};
+
+use super::{Backend, Filter, GrayImage, Result};
+use crate::error::BackendError;
+use crate::{ChromaSubsampling, OutputFormat, Rect};
+
+/// 基于 image crate 的纯 Rust 后端，图片是 RGB8 或 RGBA8 的 `DynamicImage`
+pub(crate) struct ImageBackend;
+
+/// 只处理 RGB8 和 RGBA8 两种格式
+fn normalize(im: DynamicImage, keep_alpha: bool) -> DynamicImage {
+    match im {
+        DynamicImage::ImageRgb8(_) => im,
+        DynamicImage::ImageRgba8(_) if keep_alpha => im,
+        im if keep_alpha && im.color().has_alpha() => DynamicImage::ImageRgba8(im.into_rgba8()),
+        im => DynamicImage::ImageRgb8(im.into_rgb8()),
+    }
+}
+
+fn dimension(value: i32) -> Result<u32> {
+    if value <= 0 {
+        return Err(BackendError::new(format!("invalid dimension: {}", value)));
+    }
+    Ok(value as u32)
+}
+
+/// rect 完整地落在 (width, height) 的图片中
+fn check_bounds(rect: Rect, (width, height): (i32, i32)) -> Result<()> {
+    if rect.is_empty() || (rect & Rect::new(0, 0, width, height)) != rect {
+        return Err(BackendError::new(format!(
+            "{:?} is out of image {} x {}",
+            rect, width, height
+        )));
+    }
+    Ok(())
+}
+
+/// 按照覆盖率 coverage（0~1）把颜色混合到像素上
+fn blend(pixel: &mut [u8], rgb: [u8; 3], coverage: f32) {
+    for (c, v) in pixel.iter_mut().zip(&rgb) {
+        *c = (*v as f32 * coverage + *c as f32 * (1. - coverage)).round() as u8;
+    }
+    if let Some(alpha) = pixel.get_mut(3) {
+        *alpha = (255. * coverage + *alpha as f32 * (1. - coverage)).round() as u8;
+    }
+}
+
+/// 点 (px, py) 到线段的距离
+fn distance_to_segment(px: f32, py: f32, (x0, y0): (f32, f32), (x1, y1): (f32, f32)) -> f32 {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0. {
+        0.
+    } else {
+        (((px - x0) * dx + (py - y0) * dy) / len2).clamp(0., 1.)
+    };
+    let (cx, cy) = (x0 + t * dx, y0 + t * dy);
+    ((px - cx) * (px - cx) + (py - cy) * (py - cy)).sqrt()
+}
+
+impl Backend for ImageBackend {
+    type Image = DynamicImage;
+
+    fn decode(bytes: &[u8], factor: i32, keep_alpha: bool) -> Result<DynamicImage> {
+        // 不能在解码时缩小，解码后再缩小
+        let im = normalize(image::load_from_memory(bytes)?, keep_alpha);
+        if factor <= 1 {
+            return Ok(im);
+        }
+        let factor = factor as u32;
+        let width = im.width().div_ceil(factor);
+        let height = im.height().div_ceil(factor);
+        debug!("shrink to 1/{} after decoding", factor);
+        Ok(im.resize_exact(width, height, FilterType::Triangle))
+    }
+
+    fn from_rgba(rgba: &[u8], width: i32, height: i32) -> Result<DynamicImage> {
+        let im = RgbaImage::from_raw(dimension(width)?, dimension(height)?, rgba.to_vec())
+            .ok_or_else(|| BackendError::new("rgba buffer is too small"))?;
+        Ok(DynamicImage::ImageRgba8(im))
+    }
+
+    fn filled(width: i32, height: i32, rgb: [u8; 3], alpha: Option<u8>) -> Result<DynamicImage> {
+        let (width, height) = (dimension(width)?, dimension(height)?);
+        let [r, g, b] = rgb;
+        Ok(match alpha {
+            Some(a) => {
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([r, g, b, a])))
+            }
+            None => DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb(rgb))),
+        })
+    }
+
+    fn size(im: &DynamicImage) -> (i32, i32) {
+        (im.width() as i32, im.height() as i32)
+    }
+
+    fn copy(im: &DynamicImage) -> Result<DynamicImage> {
+        Ok(im.clone())
+    }
+
+    fn resize(
+        im: &DynamicImage,
+        roi: Rect,
+        width: i32,
+        height: i32,
+        filter: Filter,
+    ) -> Result<DynamicImage> {
+        check_bounds(roi, Self::size(im))?;
+        // 缩小时三角形滤波器会按照比例扩大采样范围，效果接近区域平均
+        let filter = match filter {
+            Filter::Linear | Filter::Area => FilterType::Triangle,
+        };
+        let cropped = im.crop_imm(
+            roi.x as u32,
+            roi.y as u32,
+            roi.width as u32,
+            roi.height as u32,
+        );
+        Ok(cropped.resize_exact(dimension(width)?, dimension(height)?, filter))
+    }
+
+    fn gaussian_blur(im: &DynamicImage, sigma: f64) -> Result<DynamicImage> {
+        Ok(im.blur(sigma as f32))
+    }
+
+    fn flip(im: DynamicImage, horizontal: bool, vertical: bool) -> Result<DynamicImage> {
+        Ok(match (horizontal, vertical) {
+            (true, true) => im.rotate180(),
+            (true, false) => im.fliph(),
+            (false, true) => im.flipv(),
+            (false, false) => im,
+        })
+    }
+
+    fn transpose(im: DynamicImage) -> Result<DynamicImage> {
+        Ok(im.rotate90().fliph())
+    }
+
+    fn gray(im: &DynamicImage, max_size: i32) -> Result<GrayImage> {
+        let size = im.width().max(im.height());
+        let gray = if size as i32 > max_size {
+            let scale = max_size as f64 / size as f64;
+            let width = ((im.width() as f64 * scale).round() as u32).max(1);
+            let height = ((im.height() as f64 * scale).round() as u32).max(1);
+            im.resize_exact(width, height, FilterType::Triangle)
+                .to_luma8()
+        } else {
+            im.to_luma8()
+        };
+        Ok(GrayImage {
+            width: gray.width() as usize,
+            height: gray.height() as usize,
+            data: gray.into_raw(),
+        })
+    }
+
+    fn paste(im: &DynamicImage, canvas: &mut DynamicImage, rect: Rect) -> Result<()> {
+        check_bounds(rect, Self::size(canvas))?;
+        if Self::size(im) != (rect.width, rect.height) {
+            return Err(BackendError::new(format!(
+                "cannot paste {} x {} image into {:?}",
+                im.width(),
+                im.height(),
+                rect
+            )));
+        }
+        let (x0, y0) = (rect.x as u32, rect.y as u32);
+        match (im, canvas) {
+            (DynamicImage::ImageRgba8(src), DynamicImage::ImageRgb8(dst)) => {
+                for (x, y, s) in src.enumerate_pixels() {
+                    let d = dst.get_pixel_mut(x0 + x, y0 + y);
+                    let a = s.0[3] as u32;
+                    for (dc, sc) in d.0.iter_mut().zip(&s.0[..3]) {
+                        *dc = ((*sc as u32 * a + *dc as u32 * (255 - a) + 127) / 255) as u8;
+                    }
+                }
+            }
+            (DynamicImage::ImageRgba8(src), DynamicImage::ImageRgba8(dst)) => {
+                // 两张都有透明度，按照 over 运算合成
+                for (x, y, s) in src.enumerate_pixels() {
+                    let d = dst.get_pixel_mut(x0 + x, y0 + y);
+                    let sa = s.0[3] as u32;
+                    let da = d.0[3] as u32 * (255 - sa) / 255;
+                    let alpha = sa + da;
+                    if alpha == 0 {
+                        continue;
+                    }
+                    for (dc, sc) in d.0.iter_mut().zip(&s.0[..3]) {
+                        *dc = ((*sc as u32 * sa + *dc as u32 * da + alpha / 2) / alpha) as u8;
+                    }
+                    d.0[3] = alpha as u8;
+                }
+            }
+            (DynamicImage::ImageRgb8(src), DynamicImage::ImageRgba8(dst)) => {
+                for (x, y, s) in src.enumerate_pixels() {
+                    let [r, g, b] = s.0;
+                    dst.put_pixel(x0 + x, y0 + y, Rgba([r, g, b, 255]));
+                }
+            }
+            (DynamicImage::ImageRgb8(src), DynamicImage::ImageRgb8(dst)) => {
+                image::imageops::replace(dst, src, x0 as i64, y0 as i64);
+            }
+            _ => return Err(BackendError::new("unsupported pixel format")),
+        }
+        Ok(())
+    }
+
+    fn fill_rect(canvas: &mut DynamicImage, rect: Rect, rgb: [u8; 3]) -> Result<()> {
+        let rect = rect & Rect::new(0, 0, canvas.width() as i32, canvas.height() as i32);
+        let [r, g, b] = rgb;
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                match canvas {
+                    DynamicImage::ImageRgb8(dst) => dst.put_pixel(x as u32, y as u32, Rgb(rgb)),
+                    DynamicImage::ImageRgba8(dst) => {
+                        dst.put_pixel(x as u32, y as u32, Rgba([r, g, b, 255]))
+                    }
+                    _ => return Err(BackendError::new("unsupported pixel format")),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_line(
+        canvas: &mut DynamicImage,
+        from: (i32, i32),
+        to: (i32, i32),
+        rgb: [u8; 3],
+        thickness: i32,
+    ) -> Result<()> {
+        // 按照像素中心到线段的距离计算覆盖率，边缘是抗锯齿的
+        let half = thickness.max(1) as f32 / 2.;
+        let margin = half.ceil() as i32 + 1;
+        let bound = Rect::new(
+            from.0.min(to.0) - margin,
+            from.1.min(to.1) - margin,
+            (from.0 - to.0).abs() + margin * 2 + 1,
+            (from.1 - to.1).abs() + margin * 2 + 1,
+        ) & Rect::new(0, 0, canvas.width() as i32, canvas.height() as i32);
+        let from = (from.0 as f32, from.1 as f32);
+        let to = (to.0 as f32, to.1 as f32);
+        for y in bound.y..bound.y + bound.height {
+            for x in bound.x..bound.x + bound.width {
+                let distance = distance_to_segment(x as f32, y as f32, from, to);
+                let coverage = (half + 0.5 - distance).clamp(0., 1.);
+                if coverage <= 0. {
+                    continue;
+                }
+                match canvas {
+                    DynamicImage::ImageRgb8(dst) => {
+                        blend(&mut dst.get_pixel_mut(x as u32, y as u32).0, rgb, coverage)
+                    }
+                    DynamicImage::ImageRgba8(dst) => {
+                        blend(&mut dst.get_pixel_mut(x as u32, y as u32).0, rgb, coverage)
+                    }
+                    _ => return Err(BackendError::new("unsupported pixel format")),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_rgb(im: &DynamicImage) -> Result<Vec<u8>> {
+        Ok(im.to_rgb8().into_raw())
+    }
+
+    fn encode(im: &DynamicImage, format: &OutputFormat) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match *format {
+            OutputFormat::Jpeg {
+                quality,
+                progressive,
+                chroma_subsampling,
+            } => {
+                let rgb = im.to_rgb8();
+                let too_large = || BackendError::new("image is too large for jpeg");
+                let width = u16::try_from(rgb.width()).map_err(|_| too_large())?;
+                let height = u16::try_from(rgb.height()).map_err(|_| too_large())?;
+                let mut encoder = jpeg_encoder::Encoder::new(&mut buf, quality.clamp(1, 100) as u8);
+                encoder.set_progressive(progressive);
+                if let Some(chroma_subsampling) = chroma_subsampling {
+                    encoder.set_sampling_factor(match chroma_subsampling {
+                        ChromaSubsampling::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+                        ChromaSubsampling::Yuv422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+                        ChromaSubsampling::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+                    });
+                }
+                encoder
+                    .encode(&rgb, width, height, jpeg_encoder::ColorType::Rgb)
+                    .map_err(BackendError::wrap)?;
+            }
+            OutputFormat::Png { compression } => {
+                let compression = match compression.clamp(0, 9) {
+                    0 => png::CompressionType::Uncompressed,
+                    level => png::CompressionType::Level(level as u8),
+                };
+                im.write_with_encoder(png::PngEncoder::new_with_quality(
+                    &mut buf,
+                    compression,
+                    png::FilterType::Adaptive,
+                ))?;
+            }
+            #[cfg(feature = "webp")]
+            OutputFormat::WebP {
+                quality,
+                lossless: false,
+            } => {
+                let (width, height) = (im.width(), im.height());
+                let quality = quality.clamp(1, 100) as f32;
+                let encoded = match im {
+                    DynamicImage::ImageRgba8(rgba) => {
+                        webp::Encoder::from_rgba(rgba, width, height).encode(quality)
+                    }
+                    im => webp::Encoder::from_rgb(&im.to_rgb8(), width, height).encode(quality),
+                };
+                buf.extend_from_slice(&encoded);
+            }
+            OutputFormat::WebP { lossless, .. } => {
+                // image crate 只能无损编码 WebP
+                if !lossless {
+                    warn!("lossy webp needs the `webp` feature, encode losslessly");
+                }
+                im.write_with_encoder(WebPEncoder::new_lossless(&mut buf))?;
+            }
+            OutputFormat::Bmp => im.write_with_encoder(bmp::BmpEncoder::new(&mut buf))?,
+            OutputFormat::Tiff => {
+                im.write_with_encoder(tiff::TiffEncoder::new(Cursor::new(&mut buf)))?
+            }
+        }
+        Ok(buf)
+    }
+}
@@ -0,0 +1,103 @@
+//! 图像处理后端：解码、缩放、裁剪、合成、编码。
+//!
+//! 拼图流程只通过 [`Backend`] 处理像素，由 cargo feature 选择实现：
+//! `opencv`（默认）使用系统的 OpenCV，`image` 是纯 Rust 实现；同时启用时使用 OpenCV。
+
+use crate::error::BackendError;
+use crate::{OutputFormat, Rect};
+
+#[cfg(not(any(feature = "opencv", feature = "image")))]
+compile_error!("either the `opencv` or the `image` feature must be enabled");
+
+#[cfg(feature = "image")]
+#[cfg_attr(feature = "opencv", allow(dead_code))]
+mod image;
+#[cfg(feature = "opencv")]
+mod opencv;
+
+/// 当前使用的后端
+#[cfg(feature = "opencv")]
+pub(crate) type Active = self::opencv::OpenCvBackend;
+#[cfg(not(feature = "opencv"))]
+pub(crate) type Active = self::image::ImageBackend;
+
+/// 当前后端的图片
+pub(crate) type Image = <Active as Backend>::Image;
+
+pub(crate) type Result<T, E = BackendError> = std::result::Result<T, E>;
+
+/// 缩放时的插值方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Filter {
+    /// 双线性
+    Linear,
+    /// 区域平均，大幅缩小时没有摩尔纹
+    Area,
+}
+
+/// 8 位灰度图
+pub(crate) struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+/// 图像处理后端。图片是 3 通道的，或者是带透明通道的 4 通道；颜色都是 RGB 顺序
+pub(crate) trait Backend {
+    type Image: Send;
+
+    /// 解码，不处理 EXIF 方向；至少缩小为 1/factor（1、2、4、8）。
+    /// keep_alpha 为 true 且图片有透明度时解码为 4 通道
+    fn decode(bytes: &[u8], factor: i32, keep_alpha: bool) -> Result<Self::Image>;
+
+    /// 从 RGBA 像素生成 4 通道的图片
+    fn from_rgba(rgba: &[u8], width: i32, height: i32) -> Result<Self::Image>;
+
+    /// 纯色图片；alpha 为 None 时是 3 通道
+    fn filled(width: i32, height: i32, rgb: [u8; 3], alpha: Option<u8>) -> Result<Self::Image>;
+
+    /// (width, height)
+    fn size(im: &Self::Image) -> (i32, i32);
+
+    fn copy(im: &Self::Image) -> Result<Self::Image>;
+
+    /// 把 roi 区域缩放到 (width, height)
+    fn resize(
+        im: &Self::Image,
+        roi: Rect,
+        width: i32,
+        height: i32,
+        filter: Filter,
+    ) -> Result<Self::Image>;
+
+    fn gaussian_blur(im: &Self::Image, sigma: f64) -> Result<Self::Image>;
+
+    /// 水平和（或）垂直翻转
+    fn flip(im: Self::Image, horizontal: bool, vertical: bool) -> Result<Self::Image>;
+
+    /// 沿主对角线翻转，即转置
+    fn transpose(im: Self::Image) -> Result<Self::Image>;
+
+    /// 缩小到最大边长不超过 max_size 的灰度图
+    fn gray(im: &Self::Image, max_size: i32) -> Result<GrayImage>;
+
+    /// 把 im 画到 canvas 的 rect 区域，im 带透明通道时与 canvas 混合
+    fn paste(im: &Self::Image, canvas: &mut Self::Image, rect: Rect) -> Result<()>;
+
+    /// 用不透明的颜色填充 canvas 上的 rect 区域
+    fn fill_rect(canvas: &mut Self::Image, rect: Rect, rgb: [u8; 3]) -> Result<()>;
+
+    /// 在 canvas 上画一条抗锯齿的线段
+    fn draw_line(
+        canvas: &mut Self::Image,
+        from: (i32, i32),
+        to: (i32, i32),
+        rgb: [u8; 3],
+        thickness: i32,
+    ) -> Result<()>;
+
+    /// 3 通道图片的 RGB 像素
+    fn to_rgb(im: &Self::Image) -> Result<Vec<u8>>;
+
+    fn encode(im: &Self::Image, format: &OutputFormat) -> Result<Vec<u8>>;
+}
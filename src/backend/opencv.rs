@@ -0,0 +1,299 @@
+use opencv::{
+    core::{self as cv_core, prelude::*, Vector},
+    imgcodecs, imgproc,
+    prelude::*,
+};
+
+use super::{Backend, Filter, GrayImage, Result};
+use crate::error::BackendError;
+use crate::{OutputFormat, Rect};
+
+/// 基于 OpenCV 的后端，图片是 BGR 或 BGRA 的 `Mat`
+pub(crate) struct OpenCvBackend;
+
+fn cv_rect(rect: Rect) -> cv_core::Rect {
+    cv_core::Rect::new(rect.x, rect.y, rect.width, rect.height)
+}
+
+/// RGB 颜色转为 OpenCV 使用的 BGR 顺序
+fn scalar(rgb: [u8; 3], alpha: u8) -> cv_core::Scalar {
+    let [r, g, b] = rgb;
+    cv_core::Scalar::new(b as f64, g as f64, r as f64, alpha as f64)
+}
+
+/// 缩小为 1/factor 解码，factor 为 1、2、4、8
+fn reduced_flag(factor: i32) -> i32 {
+    match factor {
+        8 => imgcodecs::IMREAD_REDUCED_COLOR_8,
+        4 => imgcodecs::IMREAD_REDUCED_COLOR_4,
+        2 => imgcodecs::IMREAD_REDUCED_COLOR_2,
+        _ => imgcodecs::IMREAD_COLOR,
+    }
+}
+
+/// imdecode 解码失败时不报错，只返回空图
+fn non_empty(im: Mat) -> Result<Mat> {
+    if im.empty()? {
+        return Err(BackendError::new("imdecode returned an empty image"));
+    }
+    Ok(im)
+}
+
+impl Backend for OpenCvBackend {
+    type Image = Mat;
+
+    fn decode(bytes: &[u8], factor: i32, keep_alpha: bool) -> Result<Mat> {
+        let src = Mat::from_slice(bytes).map_err(|e| {
+            info!("Mat::from_slice error: {}", e);
+            debug!("{:?}", e);
+            e
+        })?;
+        if !keep_alpha {
+            let flag = reduced_flag(factor) | imgcodecs::IMREAD_IGNORE_ORIENTATION;
+            return non_empty(imgcodecs::imdecode(&src, flag)?);
+        }
+
+        // IMREAD_UNCHANGED 不能在解码时缩小
+        let mut im = non_empty(imgcodecs::imdecode(&src, imgcodecs::IMREAD_UNCHANGED)?)?;
+        if im.depth()? != cv_core::CV_8U {
+            // 16 位的 PNG
+            let mut output = Mat::default();
+            im.convert_to(&mut output, cv_core::CV_8U, 1. / 257., 0.)?;
+            im = output;
+        }
+        if im.channels()? == 1 {
+            let mut output = Mat::default();
+            imgproc::cvt_color(&im, &mut output, imgproc::COLOR_GRAY2BGR, 0)?;
+            im = output;
+        }
+        if factor <= 1 {
+            return Ok(im);
+        }
+        debug!("shrink to 1/{} after decoding", factor);
+        let scale = 1. / factor as f64;
+        let mut output = Mat::default();
+        imgproc::resize(
+            &im,
+            &mut output,
+            cv_core::Size::new(0, 0),
+            scale,
+            scale,
+            imgproc::INTER_AREA,
+        )?;
+        Ok(output)
+    }
+
+    fn from_rgba(rgba: &[u8], width: i32, height: i32) -> Result<Mat> {
+        let mut im = Mat::new_rows_cols_with_default(
+            height,
+            width,
+            cv_core::CV_8UC4,
+            cv_core::Scalar::all(0.),
+        )?;
+        for (dst, src) in im
+            .data_typed_mut::<cv_core::Vec4b>()?
+            .iter_mut()
+            .zip(rgba.chunks_exact(4))
+        {
+            dst.0 = [src[2], src[1], src[0], src[3]];
+        }
+        Ok(im)
+    }
+
+    fn filled(width: i32, height: i32, rgb: [u8; 3], alpha: Option<u8>) -> Result<Mat> {
+        let typ = match alpha {
+            Some(_) => cv_core::CV_8UC4,
+            None => cv_core::CV_8UC3,
+        };
+        Ok(Mat::new_rows_cols_with_default(
+            height,
+            width,
+            typ,
+            scalar(rgb, alpha.unwrap_or(255)),
+        )?)
+    }
+
+    fn size(im: &Mat) -> (i32, i32) {
+        (im.cols(), im.rows())
+    }
+
+    fn copy(im: &Mat) -> Result<Mat> {
+        let mut output = Mat::default();
+        im.copy_to(&mut output)?;
+        Ok(output)
+    }
+
+    fn resize(im: &Mat, roi: Rect, width: i32, height: i32, filter: Filter) -> Result<Mat> {
+        let interpolation = match filter {
+            Filter::Linear => imgproc::INTER_LINEAR,
+            Filter::Area => imgproc::INTER_AREA,
+        };
+        let im = Mat::roi(im, cv_rect(roi))?;
+        let mut output = Mat::default();
+        imgproc::resize(
+            &im,
+            &mut output,
+            cv_core::Size::new(width, height),
+            0.,
+            0.,
+            interpolation,
+        )?;
+        Ok(output)
+    }
+
+    fn gaussian_blur(im: &Mat, sigma: f64) -> Result<Mat> {
+        let mut output = Mat::default();
+        imgproc::gaussian_blur(
+            im,
+            &mut output,
+            cv_core::Size::new(0, 0),
+            sigma,
+            sigma,
+            cv_core::BORDER_REFLECT,
+        )?;
+        Ok(output)
+    }
+
+    fn flip(im: Mat, horizontal: bool, vertical: bool) -> Result<Mat> {
+        let code = match (horizontal, vertical) {
+            (true, true) => -1,
+            (true, false) => 1,
+            (false, true) => 0,
+            (false, false) => return Ok(im),
+        };
+        let mut output = Mat::default();
+        cv_core::flip(&im, &mut output, code)?;
+        Ok(output)
+    }
+
+    fn transpose(im: Mat) -> Result<Mat> {
+        let mut output = Mat::default();
+        cv_core::transpose(&im, &mut output)?;
+        Ok(output)
+    }
+
+    fn gray(im: &Mat, max_size: i32) -> Result<GrayImage> {
+        let code = match im.channels()? {
+            4 => imgproc::COLOR_BGRA2GRAY,
+            _ => imgproc::COLOR_BGR2GRAY,
+        };
+        let mut gray = Mat::default();
+        imgproc::cvt_color(im, &mut gray, code, 0)?;
+        let size = im.cols().max(im.rows());
+        if size > max_size {
+            let scale = max_size as f64 / size as f64;
+            let mut output = Mat::default();
+            imgproc::resize(
+                &gray,
+                &mut output,
+                cv_core::Size::new(0, 0),
+                scale,
+                scale,
+                imgproc::INTER_AREA,
+            )?;
+            gray = output;
+        }
+        Ok(GrayImage {
+            width: gray.cols() as usize,
+            height: gray.rows() as usize,
+            data: gray.data_typed::<u8>()?.to_vec(),
+        })
+    }
+
+    fn paste(im: &Mat, canvas: &mut Mat, rect: Rect) -> Result<()> {
+        let mut roi = Mat::roi(canvas, cv_rect(rect))?;
+        match (im.channels()?, roi.channels()?) {
+            (4, 3) => {
+                for y in 0..im.rows() {
+                    let src = im.at_row::<cv_core::Vec4b>(y)?;
+                    let dst = roi.at_row_mut::<cv_core::Vec3b>(y)?;
+                    for (d, s) in dst.iter_mut().zip(src) {
+                        let a = s.0[3] as u32;
+                        for (dc, sc) in d.0.iter_mut().zip(&s.0[..3]) {
+                            *dc = ((*sc as u32 * a + *dc as u32 * (255 - a) + 127) / 255) as u8;
+                        }
+                    }
+                }
+            }
+            (4, _) => {
+                // 两张都有透明度，按照 over 运算合成
+                for y in 0..im.rows() {
+                    let src = im.at_row::<cv_core::Vec4b>(y)?;
+                    let dst = roi.at_row_mut::<cv_core::Vec4b>(y)?;
+                    for (d, s) in dst.iter_mut().zip(src) {
+                        let sa = s.0[3] as u32;
+                        let da = d.0[3] as u32 * (255 - sa) / 255;
+                        let alpha = sa + da;
+                        if alpha == 0 {
+                            continue;
+                        }
+                        for (dc, sc) in d.0.iter_mut().zip(&s.0[..3]) {
+                            *dc = ((*sc as u32 * sa + *dc as u32 * da + alpha / 2) / alpha) as u8;
+                        }
+                        d.0[3] = alpha as u8;
+                    }
+                }
+            }
+            (_, 4) => {
+                let mut bgra = Mat::default();
+                imgproc::cvt_color(im, &mut bgra, imgproc::COLOR_BGR2BGRA, 0)?;
+                bgra.copy_to(&mut roi)?;
+            }
+            _ => im.copy_to(&mut roi)?,
+        }
+        Ok(())
+    }
+
+    fn fill_rect(canvas: &mut Mat, rect: Rect, rgb: [u8; 3]) -> Result<()> {
+        imgproc::rectangle(
+            canvas,
+            cv_rect(rect),
+            scalar(rgb, 255),
+            imgproc::FILLED,
+            imgproc::LINE_8,
+            0,
+        )?;
+        Ok(())
+    }
+
+    fn draw_line(
+        canvas: &mut Mat,
+        from: (i32, i32),
+        to: (i32, i32),
+        rgb: [u8; 3],
+        thickness: i32,
+    ) -> Result<()> {
+        imgproc::line(
+            canvas,
+            cv_core::Point::new(from.0, from.1),
+            cv_core::Point::new(to.0, to.1),
+            scalar(rgb, 255),
+            thickness,
+            imgproc::LINE_AA,
+            0,
+        )?;
+        Ok(())
+    }
+
+    fn to_rgb(im: &Mat) -> Result<Vec<u8>> {
+        let mut rgb = Mat::default();
+        let code = match im.channels()? {
+            4 => imgproc::COLOR_BGRA2RGB,
+            _ => imgproc::COLOR_BGR2RGB,
+        };
+        imgproc::cvt_color(im, &mut rgb, code, 0)?;
+        let data = rgb.data_typed::<cv_core::Vec3b>()?;
+        let mut bytes = Vec::with_capacity(data.len() * 3);
+        for pixel in data {
+            bytes.extend_from_slice(&pixel.0);
+        }
+        Ok(bytes)
+    }
+
+    fn encode(im: &Mat, format: &OutputFormat) -> Result<Vec<u8>> {
+        let mut buf = Vector::new();
+        let params: Vector<i32> = format.params().into_iter().collect();
+        imgcodecs::imencode(format.extension(), im, &mut buf, &params)?;
+        Ok(buf.to_vec())
+    }
+}
@@ -0,0 +1,43 @@
+//! 拼图 HTTP 服务，需要启用 `server` feature，接口见 `merge_images::server`：
+//!
+//! ```text
+//! MERGE_IMAGES_ADDR=0.0.0.0:8080 merge-images-server
+//! curl -F a=@1.jpg -F b=@2.jpg -F layout=waterfall localhost:8080/merge -o out.jpg
+//! ```
+//!
+//! 环境变量：`MERGE_IMAGES_ADDR` 监听地址，默认 `127.0.0.1:8080`；
+//! `MERGE_IMAGES_MAX_BODY_BYTES`、`MERGE_IMAGES_MAX_IMAGES`、`MERGE_IMAGES_MAX_CONCURRENCY`、
+//! `MERGE_IMAGES_MAX_CANVAS_PIXELS` 对应 `ServerConfig` 的各项。
+
+use std::env;
+use std::error::Error;
+use std::str::FromStr;
+
+use merge_images::server::{router, ServerConfig};
+
+fn env_or<T: FromStr>(name: &str, default: T) -> Result<T, Box<dyn Error>> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| format!("invalid {}: `{}`", name, value).into()),
+        Err(_) => Ok(default),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    pretty_env_logger::init();
+    let default = ServerConfig::default();
+    let config = ServerConfig {
+        max_body_bytes: env_or("MERGE_IMAGES_MAX_BODY_BYTES", default.max_body_bytes)?,
+        max_images: env_or("MERGE_IMAGES_MAX_IMAGES", default.max_images)?,
+        max_concurrency: env_or("MERGE_IMAGES_MAX_CONCURRENCY", default.max_concurrency)?,
+        max_canvas_pixels: env_or("MERGE_IMAGES_MAX_CANVAS_PIXELS", default.max_canvas_pixels)?,
+    };
+    let addr = env::var("MERGE_IMAGES_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    log::info!("listening on {}", addr);
+    axum::serve(listener, router(config)).await?;
+    Ok(())
+}
@@ -0,0 +1,417 @@
+//! 命令行拼图工具，需要启用 `cli` feature：
+//!
+//! ```text
+//! merge-images photos/ -l waterfall -o out.jpg
+//! cat a.png | merge-images - b.jpg "more/*.png" -f png -o - > out.png
+//! merge-images a.png b.png c.png --template layout.toml -o out.jpg
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{Parser, ValueEnum};
+use merge_images::{
+    merge_with_layout, CropStrategy, FailurePolicy, FitMode, GridLayout, HeroLayout, HeroPosition,
+    JustifiedLayout, Layout, LayoutTemplate, MergeOptions, OutputFormat, OutputSize,
+    WaterfallLayout,
+};
+
+/// 目录中会被读取的图片扩展名
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp", "tif", "tiff"];
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LayoutKind {
+    Grid,
+    Waterfall,
+    Justified,
+    Hero,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum HeroPos {
+    Top,
+    Left,
+    Center,
+}
+
+impl From<HeroPos> for HeroPosition {
+    fn from(position: HeroPos) -> Self {
+        match position {
+            HeroPos::Top => HeroPosition::Top,
+            HeroPos::Left => HeroPosition::Left,
+            HeroPos::Center => HeroPosition::Center,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Fit {
+    Cover,
+    Stretch,
+    Contain,
+    ContainBlur,
+}
+
+impl From<Fit> for FitMode {
+    fn from(fit: Fit) -> Self {
+        match fit {
+            Fit::Cover => FitMode::Cover,
+            Fit::Stretch => FitMode::Stretch,
+            Fit::Contain => FitMode::Contain,
+            Fit::ContainBlur => FitMode::ContainBlur,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Jpeg,
+    Png,
+    Webp,
+    Bmp,
+    Tiff,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Crop {
+    Center,
+    Top,
+    Saliency,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OnFailure {
+    Abort,
+    Skip,
+    Placeholder,
+}
+
+/// 把多张图片拼成一张
+#[derive(Debug, Parser)]
+#[command(name = "merge-images", version)]
+struct Args {
+    /// 图片文件、目录或者 glob（如 "photos/*.jpg"），按顺序拼接；`-` 表示从 stdin 读取一张图片
+    #[arg(required = true)]
+    inputs: Vec<String>,
+
+    /// 输出文件，`-` 表示写到 stdout
+    #[arg(short, long)]
+    output: String,
+
+    #[arg(short, long, value_enum, default_value = "grid")]
+    layout: LayoutKind,
+
+    /// 使用布局模板文件（.json 或 .toml），不能与 `--layout` 同时使用
+    #[arg(long, conflicts_with = "layout")]
+    template: Option<PathBuf>,
+
+    /// 图片如何填充格子，默认使用布局自己的方式
+    #[arg(long, value_enum)]
+    fit: Option<Fit>,
+
+    /// 图片之间的间距（像素）
+    #[arg(short, long)]
+    padding: Option<i32>,
+
+    /// 背景色，如 `#ffffff`
+    #[arg(short, long, value_parser = parse_color)]
+    background: Option<[u8; 3]>,
+
+    /// 透明背景，只对 PNG、WebP、TIFF 生效
+    #[arg(long)]
+    transparent: bool,
+
+    /// 输出格式，默认根据输出文件的扩展名决定，无法判断时为 JPEG
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// JPEG、WebP 的编码质量，1~100
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..=100))]
+    quality: Option<i32>,
+
+    /// WebP 使用无损编码
+    #[arg(long)]
+    lossless: bool,
+
+    /// 列数，只影响大于 9 图的宫格和瀑布流
+    #[arg(long)]
+    columns: Option<i32>,
+
+    /// 格子宽度，只影响大于 9 图的宫格和瀑布流
+    #[arg(long)]
+    cell_size: Option<i32>,
+
+    /// 宫格的输出宽度，格子和间距按比例缩放；只支持宫格
+    #[arg(long, conflicts_with = "max_dimension")]
+    output_width: Option<i32>,
+
+    /// 宫格输出的宽和高都不超过这个值；只支持宫格
+    #[arg(long)]
+    max_dimension: Option<i32>,
+
+    /// 等高行布局的画布宽度；主图布局中主图长边的长度
+    #[arg(long)]
+    width: Option<i32>,
+
+    /// 等高行布局的目标行高
+    #[arg(long)]
+    row_height: Option<i32>,
+
+    /// 主图布局中主图的下标（从 0 开始）
+    #[arg(long, default_value_t = 0)]
+    hero: usize,
+
+    /// 主图布局中主图的位置
+    #[arg(long, value_enum, default_value = "top")]
+    hero_position: HeroPos,
+
+    /// 输出文件的最大字节数
+    #[arg(long)]
+    max_bytes: Option<usize>,
+
+    /// 裁剪时选取区域的方式
+    #[arg(long, value_enum)]
+    crop: Option<Crop>,
+
+    /// 某张图片失败时的处理方式
+    #[arg(long, value_enum, default_value = "abort")]
+    on_failure: OnFailure,
+}
+
+/// 解析 `#rrggbb` 或 `rrggbb`
+fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(format!("expected a color like #ffffff, got `{}`", s));
+    }
+    let mut rgb = [0; 3];
+    for (i, c) in rgb.iter_mut().enumerate() {
+        *c = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|e| e.to_string())?;
+    }
+    Ok(rgb)
+}
+
+fn is_image_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// 把输入展开为文件路径；目录和 glob 按文件名排序。`-` 原样保留
+fn expand_input(input: &str) -> CliResult<Vec<PathBuf>> {
+    let path = Path::new(input);
+    if input == "-" || path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if path.is_dir() {
+        let mut paths = vec![];
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if is_image_file(&path) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        return Ok(paths);
+    }
+    if input.contains(['*', '?', '[']) {
+        let mut paths = vec![];
+        for path in glob::glob(input)? {
+            let path = path?;
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        if paths.is_empty() {
+            return Err(format!("no files match `{}`", input).into());
+        }
+        return Ok(paths);
+    }
+    Err(format!("no such file or directory: {}", input).into())
+}
+
+fn read_inputs(inputs: &[String]) -> CliResult<Vec<Vec<u8>>> {
+    let mut stdin_used = false;
+    let mut images = vec![];
+    for input in inputs {
+        for path in expand_input(input)? {
+            if path.as_os_str() == "-" {
+                if stdin_used {
+                    return Err("stdin (`-`) can only be used once".into());
+                }
+                stdin_used = true;
+                let mut buf = vec![];
+                io::stdin().read_to_end(&mut buf)?;
+                images.push(buf);
+            } else {
+                let buf = fs::read(&path)
+                    .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+                images.push(buf);
+            }
+        }
+    }
+    Ok(images)
+}
+
+/// 未指定格式时根据输出文件的扩展名决定
+fn output_format(args: &Args) -> OutputFormat {
+    let format = args.format.unwrap_or_else(|| {
+        let ext = Path::new(&args.output)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("png") => Format::Png,
+            Some("webp") => Format::Webp,
+            Some("bmp") => Format::Bmp,
+            Some("tif") | Some("tiff") => Format::Tiff,
+            _ => Format::Jpeg,
+        }
+    });
+    match format {
+        Format::Jpeg => OutputFormat::jpeg(args.quality.unwrap_or(95)),
+        Format::Png => OutputFormat::png(),
+        Format::Webp if args.lossless => OutputFormat::webp_lossless(),
+        Format::Webp => OutputFormat::webp(args.quality.unwrap_or(90)),
+        Format::Bmp => OutputFormat::Bmp,
+        Format::Tiff => OutputFormat::Tiff,
+    }
+}
+
+fn merge_options(args: &Args) -> MergeOptions {
+    let mut builder = MergeOptions::builder()
+        .format(output_format(args))
+        .transparent(args.transparent)
+        .on_failure(match args.on_failure {
+            OnFailure::Abort => FailurePolicy::Abort,
+            OnFailure::Skip => FailurePolicy::Skip,
+            OnFailure::Placeholder => FailurePolicy::Placeholder,
+        });
+    if let Some(padding) = args.padding {
+        builder = builder.padding(padding);
+    }
+    if let Some(background) = args.background {
+        builder = builder.background(background);
+    }
+    if let Some(columns) = args.columns {
+        builder = builder.columns(columns);
+    }
+    if let Some(cell_size) = args.cell_size {
+        builder = builder.cell_size(cell_size);
+    }
+    if let Some(width) = args.output_width {
+        builder = builder.output_size(OutputSize::Width(width));
+    }
+    if let Some(size) = args.max_dimension {
+        builder = builder.output_size(OutputSize::MaxDimension(size));
+    }
+    if let Some(max_bytes) = args.max_bytes {
+        builder = builder.max_bytes(max_bytes);
+    }
+    if let Some(crop) = args.crop {
+        builder = builder.crop(match crop {
+            Crop::Center => CropStrategy::Center,
+            Crop::Top => CropStrategy::Top,
+            Crop::Saliency => CropStrategy::Saliency,
+        });
+    }
+    builder.build()
+}
+
+/// 根据扩展名加载 JSON 或 TOML 格式的布局模板
+fn load_template(path: &Path) -> CliResult<LayoutTemplate> {
+    let s = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    let template = match ext.as_deref() {
+        Some("json") => LayoutTemplate::from_json(&s),
+        Some("toml") => LayoutTemplate::from_toml(&s),
+        _ => {
+            return Err(format!(
+                "unknown template format {}, expected .json or .toml",
+                path.display()
+            )
+            .into())
+        }
+    };
+    template.map_err(|e| format!("invalid template {}: {}", path.display(), e).into())
+}
+
+fn layout(args: &Args) -> CliResult<Box<dyn Layout>> {
+    if let Some(path) = &args.template {
+        return Ok(Box::new(load_template(path)?));
+    }
+    let layout: Box<dyn Layout> = match args.layout {
+        LayoutKind::Grid => {
+            let mut layout = GridLayout::default();
+            if let Some(fit) = args.fit {
+                layout = layout.fit(fit.into());
+            }
+            Box::new(layout)
+        }
+        LayoutKind::Waterfall => {
+            let mut layout = WaterfallLayout::default();
+            if let Some(fit) = args.fit {
+                layout = layout.fit(fit.into());
+            }
+            Box::new(layout)
+        }
+        LayoutKind::Justified => {
+            let default = JustifiedLayout::default();
+            let mut layout = JustifiedLayout::new(
+                args.width.unwrap_or(default.width),
+                args.row_height.unwrap_or(default.row_height),
+            );
+            if let Some(fit) = args.fit {
+                layout = layout.fit(fit.into());
+            }
+            Box::new(layout)
+        }
+        LayoutKind::Hero => {
+            let mut layout = HeroLayout::new(args.hero, args.hero_position.into());
+            if let Some(width) = args.width {
+                layout = layout.size(width);
+            }
+            if let Some(fit) = args.fit {
+                layout = layout.fit(fit.into());
+            }
+            Box::new(layout)
+        }
+    };
+    Ok(layout)
+}
+
+fn run(args: &Args) -> CliResult<()> {
+    let images = read_inputs(&args.inputs)?;
+    if images.is_empty() {
+        return Err("no input images".into());
+    }
+    let output = merge_with_layout(&images, layout(args)?.as_ref(), &merge_options(args))?;
+    if args.output == "-" {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&output.bytes)?;
+        stdout.flush()?;
+    } else {
+        fs::write(&args.output, &output.bytes)
+            .map_err(|e| format!("failed to write {}: {}", args.output, e))?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("merge-images: {}", e);
+        process::exit(1);
+    }
+}
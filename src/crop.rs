@@ -0,0 +1,246 @@
+#[cfg(feature = "opencv")]
+use std::fmt;
+#[cfg(feature = "opencv")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "opencv")]
+use opencv::{
+    core::{self as cv_core, Mat, Vector},
+    objdetect::CascadeClassifier,
+    prelude::*,
+};
+
+use crate::backend::GrayImage;
+#[cfg(feature = "opencv")]
+use crate::error::BackendError;
+use crate::prelude::*;
+
+/// 计算边缘能量时把图片缩小到的最大边长
+const ENERGY_SIZE: i32 = 256;
+/// 检测人脸时把图片缩小到的最大边长
+#[cfg(feature = "opencv")]
+const FACE_DETECT_SIZE: i32 = 640;
+/// OpenCV 自带的正脸分类器，相对于 OpenCV 的数据目录
+#[cfg(feature = "opencv")]
+const FRONTAL_FACE_CASCADE: &str = "haarcascades/haarcascade_frontalface_default.xml";
+
+/// `FitMode::Cover` 时从原图中选取区域的方式
+#[derive(Debug, Clone, Default)]
+pub enum CropStrategy {
+    /// 取中间的区域
+    #[default]
+    Center,
+    /// 竖着裁剪时偏向上方，适合人像；横着裁剪时仍然居中
+    Top,
+    /// 取边缘最多的区域，适合截图和文字
+    Saliency,
+    /// 以检测到的人脸为中心，没有人脸时按照 `Saliency` 处理；需要 `opencv` feature
+    #[cfg(feature = "opencv")]
+    Face(FaceDetector),
+}
+
+/// 人脸检测器，使用 OpenCV 的 Haar 级联分类器
+#[cfg(feature = "opencv")]
+#[derive(Clone)]
+pub struct FaceDetector {
+    classifier: Arc<Mutex<CascadeClassifier>>,
+}
+
+#[cfg(feature = "opencv")]
+impl fmt::Debug for FaceDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaceDetector").finish()
+    }
+}
+
+#[cfg(feature = "opencv")]
+impl FaceDetector {
+    /// 从级联分类器 XML 文件的内容加载，例如 OpenCV 自带的
+    /// `haarcascades/haarcascade_frontalface_default.xml`
+    pub fn from_bytes(xml: &[u8]) -> Result<Self> {
+        let invalid = |message: String| MergeError::Backend(BackendError::new(message));
+        let xml = std::str::from_utf8(xml)
+            .map_err(|e| invalid(format!("cascade is not utf-8: {}", e)))?;
+        let load = || -> opencv::Result<Option<CascadeClassifier>> {
+            let storage = cv_core::FileStorage::new(
+                xml,
+                cv_core::FileStorage_READ | cv_core::FileStorage_MEMORY,
+                "",
+            )?;
+            let mut classifier = CascadeClassifier::default()?;
+            if !classifier.read(&storage.get_first_top_level_node()?)? || classifier.empty()? {
+                return Ok(None);
+            }
+            Ok(Some(classifier))
+        };
+        match load().map_err(BackendError::from)? {
+            Some(classifier) => Ok(Self {
+                classifier: Arc::new(Mutex::new(classifier)),
+            }),
+            None => Err(invalid("failed to load cascade classifier".to_string())),
+        }
+    }
+
+    /// 加载 OpenCV 自带的正脸分类器，通过 `cv::samples::findFile` 在 OpenCV 的安装目录
+    /// 和 `OPENCV_SAMPLES_DATA_PATH` 中查找
+    pub fn frontal_face() -> Result<Self> {
+        let path =
+            cv_core::find_file(FRONTAL_FACE_CASCADE, false, true).map_err(BackendError::from)?;
+        if path.is_empty() {
+            return Err(MergeError::Backend(BackendError::new(format!(
+                "cannot find {} in the OpenCV data directory",
+                FRONTAL_FACE_CASCADE
+            ))));
+        }
+        debug!("load face cascade from {}", path);
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// 检测人脸，返回所有人脸的外接矩形
+    fn detect(&self, im: &Image) -> Result<Option<Rect>> {
+        let gray = Active::gray(im, FACE_DETECT_SIZE)?;
+        let scale = Active::size(im).0 as f64 / gray.width as f64;
+        let rows: Vec<&[u8]> = gray.data.chunks(gray.width).collect();
+        let faces = (|| -> opencv::Result<Vector<cv_core::Rect>> {
+            let gray = Mat::from_slice_2d(&rows)?;
+            let mut faces = Vector::new();
+            // 只有检测时 panic 才会 poison，分类器本身没有被修改，可以继续使用
+            let mut classifier = self
+                .classifier
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let min_size = (gray.cols().min(gray.rows()) / 20).max(20);
+            classifier.detect_multi_scale(
+                &gray,
+                &mut faces,
+                1.1,
+                3,
+                0,
+                cv_core::Size::new(min_size, min_size),
+                cv_core::Size::default(),
+            )?;
+            Ok(faces)
+        })()
+        .map_err(BackendError::from)?;
+        debug!("{} faces detected", faces.len());
+        let bound = faces.iter().fold(Rect::default(), |acc, face| {
+            acc | Rect::new(face.x, face.y, face.width, face.height)
+        });
+        if bound.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Rect::new(
+            (bound.x as f64 * scale) as i32,
+            (bound.y as f64 * scale) as i32,
+            (bound.width as f64 * scale) as i32,
+            (bound.height as f64 * scale) as i32,
+        )))
+    }
+}
+
+impl CropStrategy {
+    /// 从 im 中选出宽高比为 width : height 的区域
+    pub(crate) fn select(&self, im: &Image, width: i32, height: i32) -> Result<Rect> {
+        let (cols, rows) = Active::size(im);
+        // horizontal 为 true 时横向裁剪，即沿 x 轴选择位置
+        let (horizontal, crop, len) = if cols as i64 * height as i64 > rows as i64 * width as i64 {
+            // 图片更宽，横向裁剪
+            let crop_width = (rows as i64 * width as i64 / height as i64) as i32;
+            (true, crop_width, cols)
+        } else {
+            let crop_height = (cols as i64 * height as i64 / width as i64) as i32;
+            (false, crop_height, rows)
+        };
+        let free = len - crop;
+
+        let offset = if free <= 0 {
+            0
+        } else {
+            match self {
+                CropStrategy::Center => free / 2,
+                CropStrategy::Top if !horizontal => free / 5,
+                CropStrategy::Top => free / 2,
+                CropStrategy::Saliency => saliency_offset(im, horizontal, crop)?,
+                #[cfg(feature = "opencv")]
+                CropStrategy::Face(detector) => match detector.detect(im)? {
+                    Some(faces) => {
+                        let (start, size) = if horizontal {
+                            (faces.x, faces.width)
+                        } else {
+                            (faces.y, faces.height)
+                        };
+                        (start + size / 2 - crop / 2).clamp(0, free)
+                    }
+                    None => saliency_offset(im, horizontal, crop)?,
+                },
+            }
+        };
+        debug!("crop strategy {:?}: offset {} of {}", self, offset, free);
+        Ok(if horizontal {
+            Rect::new(offset, 0, crop, rows)
+        } else {
+            Rect::new(0, offset, cols, crop)
+        })
+    }
+}
+
+/// 3x3 Sobel 算子的梯度绝对值之和的一半，边界按照 OpenCV 的 BORDER_REFLECT_101 处理
+fn edge_energy(gray: &GrayImage) -> Vec<u8> {
+    let (width, height) = (gray.width, gray.height);
+    let reflect = |i: isize, len: usize| -> usize {
+        if len == 1 {
+            0
+        } else if i < 0 {
+            (-i) as usize
+        } else if i as usize >= len {
+            2 * (len - 1) - i as usize
+        } else {
+            i as usize
+        }
+    };
+    let at = |x: isize, y: isize| gray.data[reflect(y, height) * width + reflect(x, width)] as i32;
+    let mut energy = Vec::with_capacity(width * height);
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let dx = at(x + 1, y - 1) + 2 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2 * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let dy = at(x - 1, y + 1) + 2 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2 * at(x, y - 1)
+                - at(x + 1, y - 1);
+            let sum = dx.abs().min(255) + dy.abs().min(255);
+            energy.push(((sum + 1) / 2) as u8);
+        }
+    }
+    energy
+}
+
+/// 长度为 crop 的窗口沿着裁剪方向滑动，选出边缘能量最大的位置
+fn saliency_offset(im: &Image, horizontal: bool, crop: i32) -> Result<i32> {
+    let gray = Active::gray(im, ENERGY_SIZE)?;
+    let energy = edge_energy(&gray);
+
+    // 横向裁剪时把每一列加起来，纵向裁剪时把每一行加起来
+    let mut profile = vec![0i64; if horizontal { gray.width } else { gray.height }];
+    for (i, value) in energy.iter().enumerate() {
+        let (x, y) = (i % gray.width, i / gray.width);
+        profile[if horizontal { x } else { y }] += *value as i64;
+    }
+
+    let (cols, rows) = Active::size(im);
+    let len = if horizontal { cols } else { rows };
+    let scale = profile.len() as f64 / len as f64;
+    let window = ((crop as f64 * scale).round() as usize).clamp(1, profile.len());
+    let mut sum: i64 = profile[..window].iter().sum();
+    let (mut best, mut best_sum) = (0, sum);
+    for start in 1..=profile.len() - window {
+        sum += profile[start + window - 1] - profile[start - 1];
+        if sum > best_sum {
+            best = start;
+            best_sum = sum;
+        }
+    }
+    Ok(((best as f64 / scale).round() as i32).clamp(0, len - crop))
+}
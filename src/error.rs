@@ -0,0 +1,145 @@
+use std::fmt;
+
+/// 图像处理后端（OpenCV 或 image）返回的错误
+#[derive(Debug)]
+pub struct BackendError(Box<dyn std::error::Error + Send + Sync>);
+
+impl BackendError {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        BackendError(message.into().into())
+    }
+
+    pub(crate) fn wrap(e: impl std::error::Error + Send + Sync + 'static) -> Self {
+        BackendError(Box::new(e))
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for BackendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+#[cfg(feature = "opencv")]
+impl From<opencv::Error> for BackendError {
+    fn from(e: opencv::Error) -> Self {
+        BackendError::wrap(e)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for BackendError {
+    fn from(e: image::ImageError) -> Self {
+        BackendError::wrap(e)
+    }
+}
+
+/// 拼图过程中的错误；涉及某张输入图片时带有它的下标（从 0 开始）
+#[derive(Debug)]
+pub enum MergeError {
+    /// 没有输入图片
+    NoImages,
+    /// 第 index 张图片解码失败
+    Decode {
+        index: usize,
+        source: BackendError,
+    },
+    /// 无法识别第 index 张图片的格式
+    UnsupportedFormat {
+        index: usize,
+    },
+    /// 第 index 张图片解码后无法缩放、裁剪
+    Process {
+        index: usize,
+        source: BackendError,
+    },
+    /// 无法读出第 index 张 GIF 的帧
+    GifFrame {
+        index: usize,
+    },
+    /// 编码输出图片失败
+    Encode(BackendError),
+    /// 无法把输出图片压缩到 max_bytes 以内
+    TooLarge {
+        max_bytes: usize,
+    },
+    Io(std::io::Error),
+    /// 布局不合法
+    Layout(String),
+    /// 其他图像处理错误
+    Backend(BackendError),
+    /// 异步拼图的 future 在完成前被丢弃
+    Cancelled,
+}
+
+pub type Result<T, E = MergeError> = std::result::Result<T, E>;
+
+impl MergeError {
+    /// 出错的输入图片下标
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            MergeError::Decode { index, .. }
+            | MergeError::Process { index, .. }
+            | MergeError::UnsupportedFormat { index }
+            | MergeError::GifFrame { index } => Some(*index),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::NoImages => write!(f, "no images"),
+            MergeError::Decode { index, source } => {
+                write!(f, "failed to decode the {}-th image: {}", index, source)
+            }
+            MergeError::Process { index, source } => {
+                write!(f, "failed to process the {}-th image: {}", index, source)
+            }
+            MergeError::UnsupportedFormat { index } => {
+                write!(f, "unsupported format of the {}-th image", index)
+            }
+            MergeError::GifFrame { index } => {
+                write!(f, "failed to read frame from the {}-th image (gif)", index)
+            }
+            MergeError::Encode(e) => write!(f, "failed to encode the output: {}", e),
+            MergeError::TooLarge { max_bytes } => {
+                write!(f, "cannot encode the output into {} bytes", max_bytes)
+            }
+            MergeError::Io(e) => write!(f, "io error: {}", e),
+            MergeError::Layout(msg) => write!(f, "invalid layout: {}", msg),
+            MergeError::Backend(e) => write!(f, "image backend error: {}", e),
+            MergeError::Cancelled => write!(f, "merge cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MergeError::Decode { source, .. } | MergeError::Process { source, .. } => Some(source),
+            MergeError::Encode(e) | MergeError::Backend(e) => Some(e),
+            MergeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BackendError> for MergeError {
+    fn from(e: BackendError) -> Self {
+        MergeError::Backend(e)
+    }
+}
+
+impl From<std::io::Error> for MergeError {
+    fn from(e: std::io::Error) -> Self {
+        MergeError::Io(e)
+    }
+}
@@ -0,0 +1,182 @@
+//! C 接口，需要启用 `ffi` feature；头文件是 `include/merge_images.h`，由 cbindgen 根据本文件生成，
+//! 修改接口后见 `tests/test_ffi.rs` 的 `test_ffi_header_is_up_to_date` 更新。
+//! 动态库需要单独构建：
+//!
+//! ```text
+//! cargo rustc --lib --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! ```c
+//! MergeImagesBuffer inputs[2] = {{jpg, jpg_len}, {png, png_len}};
+//! MergeImagesResult result = merge_images_grid(inputs, 2);
+//! if (result.code == MERGE_IMAGES_OK) {
+//!     fwrite(result.data, 1, result.len, out);
+//! } else {
+//!     fprintf(stderr, "%s\n", result.message);
+//! }
+//! merge_images_free(&result);
+//! ```
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::MergeError;
+
+/// 成功
+pub const MERGE_IMAGES_OK: i32 = 0;
+/// 参数不合法，如 inputs 为空指针
+pub const MERGE_IMAGES_INVALID_ARGUMENT: i32 = 1;
+/// 没有输入图片
+pub const MERGE_IMAGES_NO_IMAGES: i32 = 2;
+/// 某张图片解码失败或格式不支持
+pub const MERGE_IMAGES_DECODE_ERROR: i32 = 3;
+/// 图片处理失败
+pub const MERGE_IMAGES_PROCESS_ERROR: i32 = 4;
+/// 编码输出图片失败
+pub const MERGE_IMAGES_ENCODE_ERROR: i32 = 5;
+/// 其他错误
+pub const MERGE_IMAGES_OTHER_ERROR: i32 = 6;
+/// 内部错误（panic）
+pub const MERGE_IMAGES_PANIC: i32 = 7;
+
+/// 调用者持有的一段输入数据
+#[repr(C)]
+pub struct MergeImagesBuffer {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// 拼图结果，用完后必须调用 `merge_images_free` 释放
+#[repr(C)]
+pub struct MergeImagesResult {
+    /// `MERGE_IMAGES_OK` 或错误码
+    pub code: i32,
+    /// 成功时是编码后的图片，失败时为 NULL
+    pub data: *mut u8,
+    pub len: usize,
+    /// 失败时是 NUL 结尾的 UTF-8 错误信息，成功时为 NULL
+    pub message: *mut c_char,
+}
+
+impl MergeImagesResult {
+    fn ok(bytes: Vec<u8>) -> Self {
+        // 转为 Box<[u8]>，释放时只需要长度
+        let bytes = bytes.into_boxed_slice();
+        let len = bytes.len();
+        Self {
+            code: MERGE_IMAGES_OK,
+            data: Box::into_raw(bytes) as *mut u8,
+            len,
+            message: ptr::null_mut(),
+        }
+    }
+
+    fn error(code: i32, message: &str) -> Self {
+        let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+        Self {
+            code,
+            data: ptr::null_mut(),
+            len: 0,
+            message: message.into_raw(),
+        }
+    }
+}
+
+fn error_code(e: &MergeError) -> i32 {
+    match e {
+        MergeError::NoImages => MERGE_IMAGES_NO_IMAGES,
+        MergeError::Decode { .. }
+        | MergeError::UnsupportedFormat { .. }
+        | MergeError::GifFrame { .. } => MERGE_IMAGES_DECODE_ERROR,
+        MergeError::Process { .. } => MERGE_IMAGES_PROCESS_ERROR,
+        MergeError::Encode(_) | MergeError::TooLarge { .. } => MERGE_IMAGES_ENCODE_ERROR,
+        _ => MERGE_IMAGES_OTHER_ERROR,
+    }
+}
+
+/// 检查参数、捕获 panic，把 merge 的结果转为 `MergeImagesResult`
+///
+/// # Safety
+/// 见 `merge_images_grid`
+unsafe fn merge_buffers(
+    inputs: *const MergeImagesBuffer,
+    count: usize,
+    merge: impl FnOnce(&[&[u8]]) -> crate::Result<Vec<u8>>,
+) -> MergeImagesResult {
+    if inputs.is_null() && count > 0 {
+        return MergeImagesResult::error(MERGE_IMAGES_INVALID_ARGUMENT, "inputs is NULL");
+    }
+    let buffers = if count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(inputs, count)
+    };
+    let mut images = Vec::with_capacity(count);
+    for (i, buffer) in buffers.iter().enumerate() {
+        if buffer.data.is_null() {
+            if buffer.len > 0 {
+                let message = format!("inputs[{}].data is NULL", i);
+                return MergeImagesResult::error(MERGE_IMAGES_INVALID_ARGUMENT, &message);
+            }
+            images.push(&[][..]);
+        } else {
+            images.push(std::slice::from_raw_parts(buffer.data, buffer.len));
+        }
+    }
+    match panic::catch_unwind(AssertUnwindSafe(|| merge(&images))) {
+        Ok(Ok(bytes)) => MergeImagesResult::ok(bytes),
+        Ok(Err(e)) => MergeImagesResult::error(error_code(&e), &e.to_string()),
+        Err(_) => MergeImagesResult::error(MERGE_IMAGES_PANIC, "merge-images panicked"),
+    }
+}
+
+/// 宫格拼图，输出 JPEG，即 `merge_images::merge`
+///
+/// # Safety
+/// inputs 指向 count 个 `MergeImagesBuffer`，每个 data 指向 len 字节可读的内存；
+/// 调用期间这些内存不能被修改或释放
+#[no_mangle]
+pub unsafe extern "C" fn merge_images_grid(
+    inputs: *const MergeImagesBuffer,
+    count: usize,
+) -> MergeImagesResult {
+    merge_buffers(inputs, count, |images| crate::merge(images))
+}
+
+/// 瀑布流拼图，输出 JPEG，即 `merge_images::waterfall`
+///
+/// # Safety
+/// 与 `merge_images_grid` 相同
+#[no_mangle]
+pub unsafe extern "C" fn merge_images_waterfall(
+    inputs: *const MergeImagesBuffer,
+    count: usize,
+) -> MergeImagesResult {
+    merge_buffers(inputs, count, |images| crate::waterfall(images))
+}
+
+/// 释放结果中的图片和错误信息，并把它们置为 NULL；重复调用是安全的
+///
+/// # Safety
+/// result 为 NULL，或者指向由本库返回、未被修改过的 `MergeImagesResult`
+#[no_mangle]
+pub unsafe extern "C" fn merge_images_free(result: *mut MergeImagesResult) {
+    let result = match result.as_mut() {
+        Some(result) => result,
+        None => return,
+    };
+    if !result.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            result.data,
+            result.len,
+        )));
+        result.data = ptr::null_mut();
+        result.len = 0;
+    }
+    if !result.message.is_null() {
+        drop(CString::from_raw(result.message));
+        result.message = ptr::null_mut();
+    }
+}
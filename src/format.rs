@@ -0,0 +1,275 @@
+use crate::backend::Filter;
+use crate::prelude::*;
+
+/// 搜索编码质量时的下限，再低画质就不可接受了，改为缩小画布
+const MIN_QUALITY: i32 = 10;
+/// 缩小画布时的最小边长
+const MIN_DIMENSION: i32 = 16;
+
+#[cfg(feature = "opencv")]
+/// `IMWRITE_JPEG_SAMPLING_FACTOR`，OpenCV 4.5.5 才加入，旧版本的绑定里没有这个常量
+const IMWRITE_JPEG_SAMPLING_FACTOR: i32 = 7;
+
+/// JPEG 色度抽样方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    /// 4:2:0，文件最小
+    Yuv420,
+    /// 4:2:2
+    Yuv422,
+    /// 4:4:4，不抽样，适合截图和文字
+    Yuv444,
+}
+
+#[cfg(feature = "opencv")]
+impl ChromaSubsampling {
+    fn sampling_factor(self) -> i32 {
+        match self {
+            ChromaSubsampling::Yuv420 => 0x411111,
+            ChromaSubsampling::Yuv422 => 0x211111,
+            ChromaSubsampling::Yuv444 => 0x111111,
+        }
+    }
+}
+
+/// 输出格式及其编码参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg {
+        /// 0~100
+        quality: i32,
+        progressive: bool,
+        /// 为 None 时使用编码器的默认值
+        chroma_subsampling: Option<ChromaSubsampling>,
+    },
+    Png {
+        /// 0~9，越大文件越小、编码越慢
+        compression: i32,
+    },
+    /// 使用 `image` 后端时，有损编码需要启用 `webp` feature，否则改为无损编码
+    WebP {
+        /// 1~100，lossless 为 true 时忽略
+        quality: i32,
+        lossless: bool,
+    },
+    Bmp,
+    Tiff,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::jpeg(95)
+    }
+}
+
+impl OutputFormat {
+    pub fn jpeg(quality: i32) -> Self {
+        OutputFormat::Jpeg {
+            quality,
+            progressive: false,
+            chroma_subsampling: None,
+        }
+    }
+
+    pub fn png() -> Self {
+        OutputFormat::Png { compression: 3 }
+    }
+
+    pub fn webp(quality: i32) -> Self {
+        OutputFormat::WebP {
+            quality,
+            lossless: false,
+        }
+    }
+
+    pub fn webp_lossless() -> Self {
+        OutputFormat::WebP {
+            quality: 100,
+            lossless: true,
+        }
+    }
+
+    /// 文件扩展名，带 `.`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg { .. } => ".jpg",
+            OutputFormat::Png { .. } => ".png",
+            OutputFormat::WebP { .. } => ".webp",
+            OutputFormat::Bmp => ".bmp",
+            OutputFormat::Tiff => ".tiff",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg { .. } => "image/jpeg",
+            OutputFormat::Png { .. } => "image/png",
+            OutputFormat::WebP { .. } => "image/webp",
+            OutputFormat::Bmp => "image/bmp",
+            OutputFormat::Tiff => "image/tiff",
+        }
+    }
+
+    /// 能否保存透明通道
+    pub fn supports_alpha(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Png { .. } | OutputFormat::WebP { .. } | OutputFormat::Tiff
+        )
+    }
+
+    /// 有损格式实际使用的编码质量；image 后端没有启用 `webp` feature 时 WebP 只能无损编码，为 None
+    pub fn quality(&self) -> Option<i32> {
+        match *self {
+            OutputFormat::Jpeg { quality, .. } => Some(quality),
+            #[cfg(any(feature = "opencv", feature = "webp"))]
+            OutputFormat::WebP {
+                quality,
+                lossless: false,
+            } => Some(quality),
+            _ => None,
+        }
+    }
+
+    fn with_quality(mut self, new_quality: i32) -> Self {
+        match &mut self {
+            OutputFormat::Jpeg { quality, .. } | OutputFormat::WebP { quality, .. } => {
+                *quality = new_quality
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// 传给 OpenCV `imgcodecs::imencode` 的参数
+    #[cfg(feature = "opencv")]
+    pub fn params(&self) -> Vec<i32> {
+        use opencv::imgcodecs;
+        match *self {
+            OutputFormat::Jpeg {
+                quality,
+                progressive,
+                chroma_subsampling,
+            } => {
+                let mut params = vec![
+                    imgcodecs::IMWRITE_JPEG_QUALITY,
+                    quality.clamp(0, 100),
+                    imgcodecs::IMWRITE_JPEG_PROGRESSIVE,
+                    progressive as i32,
+                ];
+                if let Some(chroma_subsampling) = chroma_subsampling {
+                    params.push(IMWRITE_JPEG_SAMPLING_FACTOR);
+                    params.push(chroma_subsampling.sampling_factor());
+                }
+                params
+            }
+            OutputFormat::Png { compression } => {
+                vec![imgcodecs::IMWRITE_PNG_COMPRESSION, compression.clamp(0, 9)]
+            }
+            // OpenCV 中 quality 大于 100 即为无损
+            OutputFormat::WebP { lossless: true, .. } => vec![imgcodecs::IMWRITE_WEBP_QUALITY, 101],
+            OutputFormat::WebP { quality, .. } => {
+                vec![imgcodecs::IMWRITE_WEBP_QUALITY, quality.clamp(1, 100)]
+            }
+            OutputFormat::Bmp | OutputFormat::Tiff => vec![],
+        }
+    }
+
+    /// 按照此格式编码
+    pub(crate) fn encode(&self, im: &Image) -> Result<Vec<u8>> {
+        Active::encode(im, self).map_err(MergeError::Encode)
+    }
+
+    /// 编码，并保证结果不超过 max_bytes：先降低编码质量，仍然不够时再缩小画布
+    pub(crate) fn encode_within(&self, im: &Image, max_bytes: Option<usize>) -> Result<Encoded> {
+        let max_bytes = match max_bytes {
+            Some(max_bytes) => max_bytes,
+            None => {
+                return Ok(Encoded {
+                    bytes: self.encode(im)?,
+                    quality: self.quality(),
+                    scale: 1.,
+                })
+            }
+        };
+
+        let mut scale = 1.;
+        let mut resized: Option<Image> = None;
+        loop {
+            let current = resized.as_ref().unwrap_or(im);
+            let (bytes, quality) = self.search_quality(current, max_bytes)?;
+            if bytes.len() <= max_bytes {
+                info!(
+                    "encoded into {} bytes (limit {}), quality = {:?}, scale = {}",
+                    bytes.len(),
+                    max_bytes,
+                    quality,
+                    scale
+                );
+                return Ok(Encoded {
+                    bytes,
+                    quality,
+                    scale,
+                });
+            }
+
+            // 按照文件大小估计缩放比例，大小大致与面积成正比
+            let factor = (max_bytes as f64 / bytes.len() as f64)
+                .sqrt()
+                .clamp(0.5, 0.9);
+            scale *= factor;
+            let (cols, rows) = Active::size(im);
+            let width = (cols as f64 * scale) as i32;
+            let height = (rows as f64 * scale) as i32;
+            if width.min(height) < MIN_DIMENSION {
+                return Err(MergeError::TooLarge { max_bytes });
+            }
+            debug!(
+                "{} bytes exceeds the limit {}, shrink canvas to {} x {}",
+                bytes.len(),
+                max_bytes,
+                width,
+                height
+            );
+            let output =
+                Active::resize(im, Rect::new(0, 0, cols, rows), width, height, Filter::Area)?;
+            resized = Some(output);
+        }
+    }
+
+    /// 找到不超过 max_bytes 的最高编码质量；即使最低质量也超出时返回最低质量的结果
+    fn search_quality(&self, im: &Image, max_bytes: usize) -> Result<(Vec<u8>, Option<i32>)> {
+        let bytes = self.encode(im)?;
+        let quality = match self.quality() {
+            Some(quality) if bytes.len() > max_bytes && quality > MIN_QUALITY => quality,
+            quality => return Ok((bytes, quality)),
+        };
+
+        let lowest = self.with_quality(MIN_QUALITY).encode(im)?;
+        if lowest.len() > max_bytes {
+            return Ok((lowest, Some(MIN_QUALITY)));
+        }
+        // 二分：lo 一定满足，hi 一定不满足
+        let (mut lo, mut hi) = (MIN_QUALITY, quality);
+        let mut best = lowest;
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            let bytes = self.with_quality(mid).encode(im)?;
+            trace!("quality {} => {} bytes", mid, bytes.len());
+            if bytes.len() <= max_bytes {
+                lo = mid;
+                best = bytes;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok((best, Some(lo)))
+    }
+}
+
+/// 编码结果
+pub(crate) struct Encoded {
+    pub bytes: Vec<u8>,
+    pub quality: Option<i32>,
+    pub scale: f64,
+}
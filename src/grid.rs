@@ -1,10 +1,14 @@
 use crate::prelude::*;
 use crate::utils;
-use crate::PAD;
+use crate::{
+    Cell, FitMode, ImageMeta, Layout, LayoutTemplate, MergeOptions, MergeOutput, OutputSize,
+    Placement,
+};
 
 /// 生成大于 9 图时的略缩图位置
-fn batch_image_poses(n: usize) -> ((i32, i32), Vec<Rect>) {
+fn batch_image_poses(n: usize, options: &MergeOptions) -> ((i32, i32), Vec<Rect>) {
     debug_assert!(n > 9);
+    let pad = options.padding;
     let (columns, per_size) = match n {
         0..=9 => (3, 800),
         10..=16 => (4, 500),
@@ -15,16 +19,18 @@ fn batch_image_poses(n: usize) -> ((i32, i32), Vec<Rect>) {
         65..=81 => (9, 300),
         _ => (10, 240),
     };
+    let columns = options.columns.unwrap_or(columns);
+    let per_size = options.cell_size.unwrap_or(per_size);
     let rows = (n as i32 + columns - 1) / columns;
 
-    let width = (columns * per_size) + PAD * (columns - 1);
-    let height = (rows * per_size) + PAD * (rows - 1);
+    let width = (columns * per_size) + pad * (columns - 1);
+    let height = (rows * per_size) + pad * (rows - 1);
 
     let mut rects = vec![];
     for i in 0..n as i32 {
-        let x = (i % columns) * (per_size + PAD);
+        let x = (i % columns) * (per_size + pad);
         let row = i / columns;
-        let y = row * (per_size + PAD);
+        let y = row * (per_size + pad);
         rects.push(Rect::new(x, y, per_size, per_size));
     }
 
@@ -33,132 +39,106 @@ fn batch_image_poses(n: usize) -> ((i32, i32), Vec<Rect>) {
     ((width, height), rects)
 }
 
-/// 生成 2~9 图时的略缩图位置
+/// 生成 2~9 图时的略缩图位置，使用内置的模板
+fn template_image_poses(n: usize, options: &MergeOptions) -> ((i32, i32), Vec<Rect>) {
+    if n == 1 {
+        error!("生成略缩图只有 n=1");
+    }
+    let template = LayoutTemplate::grid();
+    let placement = template.variants[n - 1].placement(options.padding);
+    let rects = placement.cells.iter().map(|cell| cell.rect).collect();
+    (placement.canvas, rects)
+}
+
+/// 把画布、格子和间距等比缩放到 size；每条边分别取整，相邻格子之间的间距保持一致
+fn scale_poses(
+    (width, height): (i32, i32),
+    rects: Vec<Rect>,
+    size: OutputSize,
+) -> ((i32, i32), Vec<Rect>) {
+    let scale = match size {
+        OutputSize::Width(target) => target as f64 / width as f64,
+        OutputSize::MaxDimension(target) => (target as f64 / width.max(height) as f64).min(1.),
+    };
+    let scaled = |v: i32| (v as f64 * scale).round() as i32;
+    let rects = rects
+        .into_iter()
+        .map(|rect| {
+            let (x, y) = (scaled(rect.x), scaled(rect.y));
+            Rect::new(
+                x,
+                y,
+                scaled(rect.x + rect.width) - x,
+                scaled(rect.y + rect.height) - y,
+            )
+        })
+        .collect();
+    debug!("scale canvas {} x {} by {:.3}", width, height, scale);
+    ((scaled(width), scaled(height)), rects)
+}
+
+/// 生成宫格的略缩图位置，按照 `options.output_size` 缩放
 /// return ((width, height), poses)
-fn image_poses(n: usize) -> ((i32, i32), Vec<Rect>) {
+fn image_poses(n: usize, options: &MergeOptions) -> ((i32, i32), Vec<Rect>) {
     debug_assert!(n >= 1);
-
-    macro_rules! rects {
-        ($(
-            ($x:expr, $y:expr, $width:expr, $height:expr),
-        )*) => {
-            vec![
-                $(
-                    Rect::new($x, $y, $width, $height),
-                )*
-            ]
-        };
+    let (canvas, rects) = if n > 9 {
+        batch_image_poses(n, options)
+    } else {
+        template_image_poses(n, options)
+    };
+    match options.output_size {
+        Some(size) => scale_poses(canvas, rects, size),
+        None => (canvas, rects),
     }
+}
 
-    match n {
-        1 => {
-            error!("生成略缩图只有 n=1");
-            ((1800, 1800), rects![(0, 0, 1800, 1800),])
+/// 宫格布局：2~9 图使用固定的排版，更多的图片排成正方形的略缩图
+#[derive(Debug, Clone, Copy)]
+pub struct GridLayout {
+    /// 图片如何填充格子，默认裁剪
+    pub fit: FitMode,
+}
+
+impl Default for GridLayout {
+    fn default() -> Self {
+        Self {
+            fit: FitMode::Cover,
         }
-        2 => (
-            (1800 + PAD, 900),
-            rects![(0, 0, 900, 900), (900 + PAD, 0, 900, 900),],
-        ),
-        // 1 + 2
-        3 => (
-            (1800 + PAD, 2700 + PAD),
-            rects![
-                (0, 0, 1800, 1800),
-                (0, 1800 + PAD, 900, 900),
-                (900 + PAD, 1800 + PAD, 900, 900),
-            ],
-        ),
-        4 => (
-            (1800 + PAD, 1800 + PAD),
-            rects![
-                (0, 0, 900, 900),
-                (0, 900 + PAD, 900, 900),
-                (900 + PAD, 0, 900, 900),
-                (900 + PAD, 900 + PAD, 900, 900),
-            ],
-        ),
-        // 1800x600 + 1800x900
-        5 => (
-            (1800 + 2 * PAD, 1500 + PAD),
-            rects![
-                (0, 0, 900, 900),
-                (900 + PAD, 0, 900, 900),
-                (0, 900 + PAD, 600, 600),
-                (600 + PAD, 900 + PAD, 600, 600),
-                (1200 + 2 * PAD, 900 + PAD, 600, 600),
-            ],
-        ),
-        // 3x2
-        6 => (
-            (1800 + 2 * PAD, 1200 + PAD),
-            rects![
-                (0, 0, 600, 600),
-                (600 + PAD, 0, 600, 600),
-                (1200 + 2 * PAD, 0, 600, 600),
-                (0, 600 + PAD, 600, 600),
-                (600 + PAD, 600 + PAD, 600, 600),
-                (1200 + 2 * PAD, 600 + PAD, 600, 600),
-            ],
-        ),
-        // 2 + 2 + 3
-        7 => (
-            (1800 + 2 * PAD, 2400 + 2 * PAD),
-            rects![
-                (0, 0, 900, 900),
-                (900 + PAD, 0, 900, 900),
-                (0, 900 + PAD, 900, 900),
-                (900 + PAD, 900 + PAD, 900, 900),
-                (0, 1800 + 2 * PAD, 600, 600),
-                (600 + PAD, 1800 + 2 * PAD, 600, 600),
-                (1200 + 2 * PAD, 1800 + 2 * PAD, 600, 600),
-            ],
-        ),
-        // 2 + 3 + 3
-        8 => (
-            (1800 + 2 * PAD, 2100 + 2 * PAD),
-            rects![
-                (0, 0, 900, 900),
-                (900 + PAD, 0, 900, 900),
-                (0, 900 + PAD, 600, 600),
-                (600 + PAD, 900 + PAD, 600, 600),
-                (1200 + 2 * PAD, 900 + PAD, 600, 600),
-                (0, 1500 + 2 * PAD, 600, 600),
-                (600 + PAD, 1500 + 2 * PAD, 600, 600),
-                (1200 + 2 * PAD, 1500 + 2 * PAD, 600, 600),
-            ],
-        ),
-        // 九宫图
-        9 => (
-            (1800 + 2 * PAD, 1800 + 2 * PAD),
-            rects![
-                (0, 0, 600, 600),
-                (600 + PAD, 0, 600, 600),
-                (1200 + 2 * PAD, 0, 600, 600),
-                (0, 600 + PAD, 600, 600),
-                (600 + PAD, 600 + PAD, 600, 600),
-                (1200 + 2 * PAD, 600 + PAD, 600, 600),
-                (0, 1200 + 2 * PAD, 600, 600),
-                (600 + PAD, 1200 + 2 * PAD, 600, 600),
-                (1200 + 2 * PAD, 1200 + 2 * PAD, 600, 600),
-            ],
-        ),
-        n => batch_image_poses(n),
     }
 }
 
-/// 返回一张拼图，格式为 jpg
-pub fn merge<T: AsRef<[u8]>>(image_bytes: &[T]) -> Result<Vec<u8>> {
-    debug!("merging {} images", image_bytes.len());
-    if image_bytes.is_empty() {
-        return Err(Error::new(1, "no images".to_string()));
+impl GridLayout {
+    pub fn fit(mut self, fit: FitMode) -> Self {
+        self.fit = fit;
+        self
     }
-    if image_bytes.len() == 1 {
-        return Ok(image_bytes[0].as_ref().to_vec());
+}
+
+impl Layout for GridLayout {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement> {
+        options.check_layout()?;
+        let (canvas, rects) = image_poses(images.len(), options);
+        let cells = rects
+            .into_iter()
+            .map(|rect| Cell {
+                rect,
+                fit: self.fit,
+            })
+            .collect();
+        Ok(Placement { canvas, cells })
     }
+}
+
+/// 返回一张拼图，格式为 jpg；需要其他格式时使用 `merge_with`
+pub fn merge<T: AsRef<[u8]>>(image_bytes: &[T]) -> Result<Vec<u8>> {
+    merge_with(image_bytes, &MergeOptions::default()).map(|output| output.bytes)
+}
 
-    utils::merge_(
-        image_bytes,
-        |image_bytes| Ok(image_poses(image_bytes.len())),
-        true,
-    )
+/// 按照 `options` 生成拼图，格式由 `options.format` 决定；
+/// 除了图片本身还会返回每张输入图片的位置，见 [`MergeOutput`]
+pub fn merge_with<T: AsRef<[u8]>>(
+    image_bytes: &[T],
+    options: &MergeOptions,
+) -> Result<MergeOutput> {
+    utils::merge_(image_bytes, &GridLayout::default(), options)
 }
@@ -0,0 +1,170 @@
+use crate::prelude::*;
+use crate::utils;
+use crate::{Cell, FitMode, ImageMeta, Layout, MergeOptions, MergeOutput, Placement};
+
+/// 主图的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeroPosition {
+    /// 主图在最上面，其余图片排在下面
+    Top,
+    /// 主图在最左边，其余图片排在右边
+    Left,
+    /// 主图在中间，其余图片分成两半排在上面和下面
+    Center,
+}
+
+/// 主图布局：选一张输入图片作为主图占据大格子，其余图片按顺序排成若干行小格子。
+///
+/// 小格子至少排 3 列，各行的格子数尽量平均；所有行的高度相同，每行的格子平分画布的宽度，
+/// 所以格子数较少的行中格子更宽，不一定是正方形。
+///
+/// 使用 `FailurePolicy::Skip` 时，主图本身解码失败会返回 `MergeError::Layout`，
+/// 不会让其他图片顶替成为主图
+#[derive(Debug, Clone, Copy)]
+pub struct HeroLayout {
+    /// 主图在输入中的下标，即 `ImageMeta::index`；跳过失败的图片后仍然指向同一张图片，
+    /// 主图本身被跳过时返回 `MergeError::Layout`
+    pub index: usize,
+    pub position: HeroPosition,
+    /// 主图长边的长度：Top、Center 时是画布宽度，Left 时是画布高度
+    pub size: i32,
+    /// 主图长边与短边的比例
+    pub ratio: f64,
+    /// 图片如何填充格子，默认裁剪
+    pub fit: FitMode,
+}
+
+impl Default for HeroLayout {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            position: HeroPosition::Top,
+            size: 1800,
+            ratio: 1.5,
+            fit: FitMode::Cover,
+        }
+    }
+}
+
+impl HeroLayout {
+    pub fn new(index: usize, position: HeroPosition) -> Self {
+        Self {
+            index,
+            position,
+            ..Default::default()
+        }
+    }
+
+    pub fn size(mut self, size: i32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn ratio(mut self, ratio: f64) -> Self {
+        self.ratio = ratio;
+        self
+    }
+
+    pub fn fit(mut self, fit: FitMode) -> Self {
+        self.fit = fit;
+        self
+    }
+}
+
+/// 在宽度为 width 的区域内从 y 开始排列 n 个格子，每行最多 columns 个，
+/// 各行的格子数尽量平均；行高是排满 columns 个格子时的宽度，每行都拉伸到填满宽度；
+/// 返回格子和下一行的 y
+fn place_rows(n: usize, columns: usize, width: i32, y: i32, pad: i32) -> (Vec<Rect>, i32) {
+    let cell = (width - pad * (columns as i32 - 1)) / columns as i32;
+    let rows = n.div_ceil(columns);
+    let mut rects = Vec::with_capacity(n);
+    let mut y = y;
+    for row in 0..rows {
+        // 前面的行多放一个
+        let count = (n / rows + (row < n % rows) as usize) as i32;
+        let cell_width = (width - pad * (count - 1)) / count;
+        for i in 0..count {
+            let x = (cell_width + pad) * i;
+            // 最后一个格子补上取整的误差
+            let w = if i + 1 == count {
+                width - x
+            } else {
+                cell_width
+            };
+            rects.push(Rect::new(x, y, w, cell));
+        }
+        y += cell + pad;
+    }
+    (rects, y)
+}
+
+impl Layout for HeroLayout {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement> {
+        let n = images.len();
+        debug!(
+            "generating hero layout for {} images, hero = {}, {:?}",
+            n, self.index, self.position
+        );
+        // images 中可能已经去掉了被跳过的图片，按照原始下标查找主图
+        let hero_pos = images
+            .iter()
+            .position(|meta| meta.index == self.index)
+            .ok_or_else(|| {
+                MergeError::Layout(format!("hero image {} is not available", self.index))
+            })?;
+        if self.size <= 0 || self.ratio.is_nan() || self.ratio < 1. {
+            return Err(MergeError::Layout(format!(
+                "invalid hero layout: size = {}, ratio = {}",
+                self.size, self.ratio
+            )));
+        }
+        options.check_layout()?;
+        options.check_no_output_size("hero")?;
+        let pad = options.padding;
+        let width = self.size;
+        let hero_height = (width as f64 / self.ratio).round().max(1.) as i32;
+        // 其余图片排成的列数，至少 3 列，保证小格子明显比主图小
+        let others = n - 1;
+        let columns = ((others as f64).sqrt().ceil() as usize).max(3);
+
+        // 先按主图在上面（或中间）排列，Left 时再转置
+        let (above, below) = match self.position {
+            HeroPosition::Top | HeroPosition::Left => (0, others),
+            HeroPosition::Center => (others / 2, others - others / 2),
+        };
+        let (mut rects, y) = place_rows(above, columns, width, 0, pad);
+        let hero = Rect::new(0, y, width, hero_height);
+        let (below, y) = place_rows(below, columns, width, y + hero_height + pad, pad);
+        rects.extend(below);
+        rects.insert(hero_pos, hero);
+        let mut canvas = (width, y - pad);
+
+        if self.position == HeroPosition::Left {
+            for rect in &mut rects {
+                *rect = Rect::new(rect.y, rect.x, rect.height, rect.width);
+            }
+            canvas = (canvas.1, canvas.0);
+        }
+        debug!("width = {}, height = {}", canvas.0, canvas.1);
+        trace!("cells = {:?}", rects);
+        let cells = rects
+            .into_iter()
+            .map(|rect| Cell {
+                rect,
+                fit: self.fit,
+            })
+            .collect();
+        Ok(Placement { canvas, cells })
+    }
+}
+
+/// 按照 `options` 生成主图拼图，第 index 张图片为主图，位置由 position 决定；
+/// 除了图片本身还会返回每张输入图片的位置，见 [`MergeOutput`]
+pub fn merge_with<T: AsRef<[u8]>>(
+    image_bytes: &[T],
+    index: usize,
+    position: HeroPosition,
+    options: &MergeOptions,
+) -> Result<MergeOutput> {
+    utils::merge_(image_bytes, &HeroLayout::new(index, position), options)
+}
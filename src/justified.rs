@@ -0,0 +1,194 @@
+use crate::prelude::*;
+use crate::utils;
+use crate::{Cell, FitMode, ImageMeta, Layout, MergeOptions, MergeOutput, Placement};
+
+/// 最后一行没有排满时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LastRow {
+    /// 和其他行一样拉伸到填满宽度
+    Justify,
+    /// 保持目标行高，靠左排列
+    Left,
+    /// 保持目标行高，居中排列
+    Center,
+}
+
+/// 等高行布局：按顺序把图片排成若干行，每行高度相同且恰好填满宽度，保持图片比例
+#[derive(Debug, Clone, Copy)]
+pub struct JustifiedLayout {
+    /// 画布宽度
+    pub width: i32,
+    /// 目标行高，实际行高会在它附近浮动
+    pub row_height: i32,
+    pub last_row: LastRow,
+    /// 图片如何填充格子；格子的比例与图片基本一致，默认裁剪掉取整的误差
+    pub fit: FitMode,
+}
+
+impl Default for JustifiedLayout {
+    fn default() -> Self {
+        Self {
+            width: 1800,
+            row_height: 400,
+            last_row: LastRow::Left,
+            fit: FitMode::Cover,
+        }
+    }
+}
+
+impl JustifiedLayout {
+    pub fn new(width: i32, row_height: i32) -> Self {
+        Self {
+            width,
+            row_height,
+            ..Default::default()
+        }
+    }
+
+    pub fn last_row(mut self, last_row: LastRow) -> Self {
+        self.last_row = last_row;
+        self
+    }
+
+    pub fn fit(mut self, fit: FitMode) -> Self {
+        self.fit = fit;
+        self
+    }
+}
+
+/// 把一行图片按照 height 的高度从 (x_offset, y) 开始排列；
+/// fill_width 不为 None 时让最后一张图片补上取整的误差，恰好填满这个宽度。
+/// 每张图片的边界按累计宽度取整，整行的宽度不会因为逐张取整而超出
+fn place_row(
+    ratios: &[f64],
+    y: i32,
+    height: i32,
+    x_offset: i32,
+    fill_width: Option<i32>,
+    pad: i32,
+    fit: FitMode,
+) -> Vec<Cell> {
+    let mut sum = 0.;
+    let mut cells = Vec::with_capacity(ratios.len());
+    for (i, ratio) in ratios.iter().enumerate() {
+        let x = x_offset + (sum * height as f64).round() as i32 + pad * i as i32;
+        sum += ratio;
+        let end = match fill_width {
+            Some(fill_width) if i + 1 == ratios.len() => fill_width,
+            _ => x_offset + (sum * height as f64).round() as i32 + pad * i as i32,
+        };
+        cells.push(Cell {
+            rect: Rect::new(x, y, (end - x).max(1), height),
+            fit,
+        });
+    }
+    cells
+}
+
+impl Layout for JustifiedLayout {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement> {
+        debug!("generating justified rows for {} images", images.len());
+        if self.width <= 0 || self.row_height <= 0 {
+            return Err(MergeError::Layout(format!(
+                "invalid justified layout: width = {}, row_height = {}",
+                self.width, self.row_height
+            )));
+        }
+        options.check_layout()?;
+        options.check_no_output_size("justified")?;
+        let pad = options.padding;
+        let mut ratios = Vec::with_capacity(images.len());
+        for image in images {
+            let (width, height) = image
+                .size
+                .ok_or(MergeError::UnsupportedFormat { index: image.index })?;
+            if width <= 0 || height <= 0 {
+                return Err(MergeError::UnsupportedFormat { index: image.index });
+            }
+            ratios.push(width as f64 / height as f64);
+        }
+
+        let mut cells = Vec::with_capacity(images.len());
+        let mut y = 0;
+        let mut row_start = 0;
+        let mut ratio_sum = 0.;
+        let mut i = 0;
+        while i < ratios.len() {
+            ratio_sum += ratios[i];
+            let gaps = pad * (i - row_start) as i32;
+            if ratio_sum * self.row_height as f64 + gaps as f64 >= self.width as f64 {
+                // 这一行排满了，算出恰好填满宽度的行高；
+                // 如果不放这张图时行高更接近目标，就把它留给下一行
+                let mut row_end = i;
+                let mut height = (self.width - gaps) as f64 / ratio_sum;
+                if i > row_start {
+                    let height_without = (self.width - gaps + pad) as f64 / (ratio_sum - ratios[i]);
+                    let target = self.row_height as f64;
+                    if (height_without - target).abs() < (height - target).abs() {
+                        row_end = i - 1;
+                        height = height_without;
+                    }
+                }
+                let height = height.round().max(1.) as i32;
+                cells.extend(place_row(
+                    &ratios[row_start..=row_end],
+                    y,
+                    height,
+                    0,
+                    Some(self.width),
+                    pad,
+                    self.fit,
+                ));
+                y += height + pad;
+                row_start = row_end + 1;
+                ratio_sum = 0.;
+                i = row_start;
+                continue;
+            }
+            i += 1;
+        }
+
+        if row_start < ratios.len() {
+            let row = &ratios[row_start..];
+            let gaps = pad * (row.len() as i32 - 1);
+            let natural_width = (ratio_sum * self.row_height as f64).round() as i32 + gaps;
+            let (height, x_offset, fill) = match self.last_row {
+                LastRow::Justify => (
+                    ((self.width - gaps) as f64 / ratio_sum).round().max(1.) as i32,
+                    0,
+                    Some(self.width),
+                ),
+                LastRow::Left => (self.row_height, 0, None),
+                LastRow::Center => (
+                    self.row_height,
+                    ((self.width - natural_width) / 2).max(0),
+                    None,
+                ),
+            };
+            cells.extend(place_row(row, y, height, x_offset, fill, pad, self.fit));
+            y += height + pad;
+        }
+
+        let height = (y - pad).max(0);
+        debug!("width = {}, height = {}", self.width, height);
+        trace!("cells = {:?}", cells);
+        Ok(Placement {
+            canvas: (self.width, height),
+            cells,
+        })
+    }
+}
+
+/// 返回一张等高行拼图，格式为 jpg；需要其他格式时使用 `merge_with`
+pub fn merge<T: AsRef<[u8]>>(image_bytes: &[T]) -> Result<Vec<u8>> {
+    merge_with(image_bytes, &MergeOptions::default()).map(|output| output.bytes)
+}
+
+/// 按照 `options` 生成等高行拼图，格式由 `options.format` 决定；
+/// 除了图片本身还会返回每张输入图片的位置，见 [`MergeOutput`]
+pub fn merge_with<T: AsRef<[u8]>>(
+    image_bytes: &[T],
+    options: &MergeOptions,
+) -> Result<MergeOutput> {
+    utils::merge_(image_bytes, &JustifiedLayout::default(), options)
+}
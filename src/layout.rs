@@ -0,0 +1,67 @@
+use crate::prelude::*;
+use crate::utils;
+use crate::{MergeOptions, MergeOutput};
+
+/// 图片的元信息，在解码之前从文件头中读出
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageMeta {
+    /// 图片在输入中的下标
+    pub index: usize,
+    /// (width, height)，无法从文件头读出时为 None
+    pub size: Option<(i32, i32)>,
+}
+
+/// 图片如何填充它的格子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum FitMode {
+    /// 从中间裁剪出与格子比例一致的区域再缩放
+    Cover,
+    /// 直接缩放到格子大小，比例不一致时会拉伸
+    Stretch,
+    /// 保持比例完整地放进格子，四周露出背景色，适合长截图
+    Contain,
+    /// 与 `Contain` 相同，但四周用模糊放大的图片填充
+    ContainBlur,
+}
+
+/// 一张图片在画布上的位置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub rect: Rect,
+    pub fit: FitMode,
+}
+
+/// 布局结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    /// (width, height)
+    pub canvas: (i32, i32),
+    /// 与输入的图片一一对应
+    pub cells: Vec<Cell>,
+}
+
+/// 布局算法：根据图片的元信息决定画布大小和每张图片的位置
+pub trait Layout {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement>;
+}
+
+/// 方便在运行时选择布局，如 `Box<dyn Layout + Send>`
+impl<L: Layout + ?Sized> Layout for Box<L> {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement> {
+        (**self).layout(images, options)
+    }
+}
+
+/// 用自定义的布局生成拼图
+pub fn merge_with_layout<T: AsRef<[u8]>, L: Layout + ?Sized>(
+    image_bytes: &[T],
+    layout: &L,
+    options: &MergeOptions,
+) -> Result<MergeOutput> {
+    utils::merge_(image_bytes, layout, options)
+}
@@ -2,19 +2,52 @@
 extern crate log;
 
 pub(crate) mod prelude {
-    pub use opencv::{
-        core::{self as cv_core, prelude::*, Rect, Vector},
-        imgcodecs, imgproc,
-        prelude::*,
-        Error, Result,
-    };
+    pub(crate) use crate::backend::{Active, Backend, Image};
+    pub use crate::error::{MergeError, Result};
+    pub use crate::Rect;
 }
 
+mod animation;
+#[cfg(feature = "tokio")]
+mod async_api;
+mod backend;
+mod crop;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod format;
 mod grid;
+mod hero;
+mod justified;
+mod layout;
+mod options;
+mod orientation;
+mod output;
+#[cfg(feature = "python")]
+mod python;
+mod rect;
+#[cfg(feature = "server")]
+pub mod server;
+mod template;
 mod utils;
 mod waterfall;
 
 pub(crate) const PAD: i32 = 10;
 
-pub use grid::merge;
-pub use waterfall::merge as waterfall;
+pub use animation::{AnimationFormat, AnimationOptions};
+#[cfg(feature = "tokio")]
+pub use async_api::{merge_async, merge_with_layout_async, waterfall_async};
+pub use crop::CropStrategy;
+#[cfg(feature = "opencv")]
+pub use crop::FaceDetector;
+pub use error::{BackendError, MergeError, Result};
+pub use format::{ChromaSubsampling, OutputFormat};
+pub use grid::{merge, merge_with, GridLayout};
+pub use hero::{merge_with as hero_with, HeroLayout, HeroPosition};
+pub use justified::{merge as justified, merge_with as justified_with, JustifiedLayout, LastRow};
+pub use layout::{merge_with_layout, Cell, FitMode, ImageMeta, Layout, Placement};
+pub use options::{FailurePolicy, MergeOptions, MergeOptionsBuilder, OutputSize};
+pub use output::MergeOutput;
+pub use rect::Rect;
+pub use template::{LayoutTemplate, TemplateCell, TemplateVariant};
+pub use waterfall::{merge as waterfall, merge_with as waterfall_with, WaterfallLayout};
@@ -0,0 +1,185 @@
+use crate::prelude::*;
+use crate::{AnimationOptions, CropStrategy, OutputFormat, PAD};
+
+/// 某张图片解码或处理失败时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// 直接返回错误
+    Abort,
+    /// 跳过这张图片，用剩下的图片重新布局
+    Skip,
+    /// 在它的格子里画一个灰色的占位图
+    Placeholder,
+}
+
+/// 宫格的输出尺寸，格子和间距按比例缩放；只有宫格支持，其他内置布局收到时返回 `MergeError::Layout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSize {
+    /// 画布宽度恰好为这个值，高度按比例计算
+    Width(i32),
+    /// 画布的宽和高都不超过这个值；本来就更小时不放大
+    MaxDimension(i32),
+}
+
+/// 拼图参数，通过 [`MergeOptions::builder`] 构造
+#[derive(Debug, Clone)]
+pub struct MergeOptions {
+    /// 图片之间的间距（像素）
+    pub padding: i32,
+    /// 画布背景色，RGB
+    pub background: [u8; 3],
+    /// 透明背景，只在输出 PNG、WebP、TIFF 时生效，其他格式仍然使用 background
+    pub transparent: bool,
+    /// 输出格式及其编码参数；只有一张图片时会原样返回输入，不重新编码
+    pub format: OutputFormat,
+    /// 列数，为 None 时根据图片数量查表决定；只影响大于 9 图的宫格和瀑布流
+    pub columns: Option<i32>,
+    /// 格子宽度，为 None 时根据图片数量查表决定；只影响大于 9 图的宫格和瀑布流
+    pub cell_size: Option<i32>,
+    /// 宫格的输出尺寸，为 None 时由图片数量决定（2~9 图约 1800 像素宽）；
+    /// 只用于宫格，瀑布流、等高行、主图和模板布局收到时返回 `MergeError::Layout`
+    pub output_size: Option<OutputSize>,
+    /// 输出文件的最大字节数；超出时先降低 JPEG/WebP 的编码质量，仍然不够再缩小画布
+    pub max_bytes: Option<usize>,
+    /// 图片需要裁剪（`FitMode::Cover`）时选取区域的方式
+    pub crop: CropStrategy,
+    /// 某张图片失败时的处理方式
+    pub on_failure: FailurePolicy,
+    /// 输出动图：输入中的 GIF 逐帧播放，此时忽略 format；为 None 时 GIF 只取第一帧
+    pub animation: Option<AnimationOptions>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            padding: PAD,
+            background: [255, 255, 255],
+            transparent: false,
+            format: OutputFormat::default(),
+            columns: None,
+            cell_size: None,
+            output_size: None,
+            max_bytes: None,
+            crop: CropStrategy::default(),
+            on_failure: FailurePolicy::Abort,
+            animation: None,
+        }
+    }
+}
+
+impl MergeOptions {
+    pub fn builder() -> MergeOptionsBuilder {
+        MergeOptionsBuilder::default()
+    }
+
+    /// 画布是否带透明通道；输出动图时不支持透明
+    pub(crate) fn transparent_canvas(&self) -> bool {
+        self.transparent && self.animation.is_none() && self.format.supports_alpha()
+    }
+
+    /// 检查 padding 不为负，columns、cell_size、output_size 都为正，
+    /// 否则格子会重叠，布局会除零或无法结束
+    pub(crate) fn check_layout(&self) -> Result<()> {
+        if self.padding < 0 {
+            return Err(MergeError::Layout(format!(
+                "padding must not be negative: {}",
+                self.padding
+            )));
+        }
+        let sizes = [
+            ("columns", self.columns),
+            ("cell_size", self.cell_size),
+            (
+                "output_size",
+                self.output_size
+                    .map(|(OutputSize::Width(size) | OutputSize::MaxDimension(size))| size),
+            ),
+        ];
+        for (name, value) in sizes {
+            if let Some(value) = value.filter(|&value| value <= 0) {
+                return Err(MergeError::Layout(format!(
+                    "{} must be positive: {}",
+                    name, value
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 宫格以外的布局不支持 output_size，直接报错而不是悄悄忽略
+    pub(crate) fn check_no_output_size(&self, layout: &str) -> Result<()> {
+        match self.output_size {
+            Some(size) => Err(MergeError::Layout(format!(
+                "output_size {:?} is only supported by the grid layout, not {}",
+                size, layout
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptionsBuilder {
+    options: MergeOptions,
+}
+
+impl MergeOptionsBuilder {
+    pub fn padding(mut self, padding: i32) -> Self {
+        self.options.padding = padding;
+        self
+    }
+
+    pub fn background(mut self, rgb: [u8; 3]) -> Self {
+        self.options.background = rgb;
+        self
+    }
+
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.options.transparent = transparent;
+        self
+    }
+
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.options.format = format;
+        self
+    }
+
+    pub fn columns(mut self, columns: i32) -> Self {
+        self.options.columns = Some(columns);
+        self
+    }
+
+    pub fn cell_size(mut self, cell_size: i32) -> Self {
+        self.options.cell_size = Some(cell_size);
+        self
+    }
+
+    pub fn output_size(mut self, size: OutputSize) -> Self {
+        self.options.output_size = Some(size);
+        self
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.options.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn crop(mut self, strategy: CropStrategy) -> Self {
+        self.options.crop = strategy;
+        self
+    }
+
+    pub fn on_failure(mut self, policy: FailurePolicy) -> Self {
+        self.options.on_failure = policy;
+        self
+    }
+
+    pub fn animation(mut self, animation: AnimationOptions) -> Self {
+        self.options.animation = Some(animation);
+        self
+    }
+
+    pub fn build(self) -> MergeOptions {
+        self.options
+    }
+}
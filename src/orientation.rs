@@ -0,0 +1,66 @@
+use std::io::Cursor;
+
+use crate::backend;
+use crate::prelude::*;
+
+/// EXIF 中的 Orientation 标签，1~8，1 表示不需要旋转
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Orientation(u32);
+
+impl Orientation {
+    /// 从图片中读出方向；没有 EXIF 或标签不合法时视为 1
+    pub fn read(bytes: &[u8]) -> Self {
+        let exif = match exif::Reader::new().read_from_container(&mut Cursor::new(bytes)) {
+            Ok(exif) => exif,
+            Err(e) => {
+                trace!("no exif: {}", e);
+                return Orientation(1);
+            }
+        };
+        match exif
+            .get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+        {
+            Some(value @ 1..=8) => {
+                debug!("exif orientation: {}", value);
+                Orientation(value)
+            }
+            value => {
+                if value.is_some() {
+                    warn!("invalid exif orientation: {:?}", value);
+                }
+                Orientation(1)
+            }
+        }
+    }
+
+    /// 摆正之后宽高是否互换
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(self.0, 5..=8)
+    }
+
+    /// 摆正之后的 (width, height)
+    pub fn apply_to_size(self, (width, height): (i32, i32)) -> (i32, i32) {
+        if self.swaps_dimensions() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// 把解码出的图片摆正
+    pub fn apply(self, im: Image) -> backend::Result<Image> {
+        Ok(match self.0 {
+            2 => Active::flip(im, true, false)?,
+            3 => Active::flip(im, true, true)?,
+            4 => Active::flip(im, false, true)?,
+            5 => Active::transpose(im)?,
+            // 顺时针旋转 90°
+            6 => Active::flip(Active::transpose(im)?, true, false)?,
+            7 => Active::flip(Active::transpose(im)?, true, true)?,
+            // 逆时针旋转 90°
+            8 => Active::flip(Active::transpose(im)?, false, true)?,
+            _ => im,
+        })
+    }
+}
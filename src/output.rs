@@ -0,0 +1,37 @@
+use crate::prelude::*;
+
+/// 拼图结果
+#[derive(Debug, Clone)]
+pub struct MergeOutput {
+    /// 编码后的图片
+    pub bytes: Vec<u8>,
+    /// 最终使用的编码质量，只有有损的 JPEG 和 WebP 才有；原样返回输入时为 None
+    pub quality: Option<i32>,
+    /// 为了满足大小限制对画布的缩放比例，1.0 表示没有缩放
+    pub scale: f64,
+    /// 缩放前的画布大小 (width, height)
+    pub canvas: (i32, i32),
+    /// 每张输入图片在画布上的位置（缩放前的坐标），被跳过的图片为 None
+    pub cells: Vec<Option<Rect>>,
+    /// 每张输入图片被裁剪后实际使用的区域，坐标相对于按照 EXIF 方向摆正后的原图
+    pub crops: Vec<Option<Rect>>,
+    /// 失败后被跳过的输入下标，见 [`FailurePolicy::Skip`](crate::FailurePolicy::Skip)
+    pub skipped: Vec<usize>,
+    /// 失败后画了占位图的输入下标，见 [`FailurePolicy::Placeholder`](crate::FailurePolicy::Placeholder)
+    pub placeholders: Vec<usize>,
+    /// 输出的帧数，静态图片为 1
+    pub frames: usize,
+}
+
+impl MergeOutput {
+    /// 输出图片上的 (x, y) 属于哪一张输入图片，可以用来做图片热区
+    pub fn index_at(&self, x: i32, y: i32) -> Option<usize> {
+        let (x, y) = (
+            (x as f64 / self.scale) as i32,
+            (y as f64 / self.scale) as i32,
+        );
+        self.cells
+            .iter()
+            .position(|cell| cell.is_some_and(|rect| rect.contains(x, y)))
+    }
+}
@@ -0,0 +1,233 @@
+//! Python 绑定，需要启用 `python` feature，用 maturin 构建（见 `pyproject.toml`）：
+//!
+//! ```python
+//! import merge_images
+//!
+//! options = merge_images.MergeOptions(format="png", padding=0)
+//! output = merge_images.waterfall([open("1.jpg", "rb").read(), open("2.png", "rb").read()], options)
+//! ```
+//!
+//! 拼图时释放 GIL，可以在多个 Python 线程中同时拼图。
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{
+    merge_with_layout, CropStrategy, FailurePolicy, FitMode, GridLayout, Layout, MergeOptions,
+    OutputFormat, WaterfallLayout, PAD,
+};
+
+create_exception!(merge_images, MergeError, PyException, "拼图失败");
+
+/// 拼图参数，对应 Rust 的 `MergeOptions`；枚举值都用小写的字符串表示
+#[pyclass(
+    name = "MergeOptions",
+    module = "merge_images",
+    get_all,
+    set_all,
+    skip_from_py_object
+)]
+#[derive(Debug, Clone)]
+struct PyMergeOptions {
+    /// 图片之间的间距（像素）
+    padding: i32,
+    /// 画布背景色 (r, g, b)
+    background: (u8, u8, u8),
+    /// 透明背景，只对 png、webp、tiff 生效
+    transparent: bool,
+    /// jpeg、png、webp、bmp、tiff
+    format: String,
+    /// jpeg、webp 的编码质量，None 时 jpeg 为 95、webp 为 90
+    quality: Option<i32>,
+    /// webp 使用无损编码
+    lossless: bool,
+    columns: Option<i32>,
+    cell_size: Option<i32>,
+    max_bytes: Option<usize>,
+    /// center、top、saliency
+    crop: String,
+    /// abort、skip、placeholder
+    on_failure: String,
+}
+
+#[pymethods]
+impl PyMergeOptions {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        padding = PAD,
+        background = (255, 255, 255),
+        transparent = false,
+        format = "jpeg".to_string(),
+        quality = None,
+        lossless = false,
+        columns = None,
+        cell_size = None,
+        max_bytes = None,
+        crop = "center".to_string(),
+        on_failure = "abort".to_string(),
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        padding: i32,
+        background: (u8, u8, u8),
+        transparent: bool,
+        format: String,
+        quality: Option<i32>,
+        lossless: bool,
+        columns: Option<i32>,
+        cell_size: Option<i32>,
+        max_bytes: Option<usize>,
+        crop: String,
+        on_failure: String,
+    ) -> Self {
+        Self {
+            padding,
+            background,
+            transparent,
+            format,
+            quality,
+            lossless,
+            columns,
+            cell_size,
+            max_bytes,
+            crop,
+            on_failure,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "MergeOptions(padding={}, background={:?}, transparent={}, format={:?}, quality={:?}, \
+             lossless={}, columns={:?}, cell_size={:?}, max_bytes={:?}, crop={:?}, on_failure={:?})",
+            self.padding,
+            self.background,
+            self.transparent,
+            self.format,
+            self.quality,
+            self.lossless,
+            self.columns,
+            self.cell_size,
+            self.max_bytes,
+            self.crop,
+            self.on_failure,
+        )
+    }
+}
+
+fn invalid(name: &str, value: &str) -> PyErr {
+    PyValueError::new_err(format!("invalid {}: {:?}", name, value))
+}
+
+impl PyMergeOptions {
+    fn output_format(&self) -> PyResult<OutputFormat> {
+        Ok(match self.format.as_str() {
+            "jpeg" | "jpg" => OutputFormat::jpeg(self.quality.unwrap_or(95)),
+            "png" => OutputFormat::png(),
+            "webp" if self.lossless => OutputFormat::webp_lossless(),
+            "webp" => OutputFormat::webp(self.quality.unwrap_or(90)),
+            "bmp" => OutputFormat::Bmp,
+            "tiff" => OutputFormat::Tiff,
+            format => return Err(invalid("format", format)),
+        })
+    }
+
+    fn to_options(&self) -> PyResult<MergeOptions> {
+        let (r, g, b) = self.background;
+        let crop = match self.crop.as_str() {
+            "center" => CropStrategy::Center,
+            "top" => CropStrategy::Top,
+            "saliency" => CropStrategy::Saliency,
+            crop => return Err(invalid("crop", crop)),
+        };
+        let on_failure = match self.on_failure.as_str() {
+            "abort" => FailurePolicy::Abort,
+            "skip" => FailurePolicy::Skip,
+            "placeholder" => FailurePolicy::Placeholder,
+            on_failure => return Err(invalid("on_failure", on_failure)),
+        };
+        let mut options = MergeOptions::builder()
+            .padding(self.padding)
+            .background([r, g, b])
+            .transparent(self.transparent)
+            .format(self.output_format()?)
+            .crop(crop)
+            .on_failure(on_failure)
+            .build();
+        options.columns = self.columns;
+        options.cell_size = self.cell_size;
+        options.max_bytes = self.max_bytes;
+        Ok(options)
+    }
+}
+
+fn fit_mode(fit: &str) -> PyResult<FitMode> {
+    Ok(match fit {
+        "cover" => FitMode::Cover,
+        "stretch" => FitMode::Stretch,
+        "contain" => FitMode::Contain,
+        "contain_blur" => FitMode::ContainBlur,
+        fit => return Err(invalid("fit", fit)),
+    })
+}
+
+/// 在释放 GIL 的情况下拼图，输入直接引用 Python 的 bytes，不复制
+fn merge_bytes<L: Layout + Sync>(
+    py: Python<'_>,
+    images: &[Bound<'_, PyBytes>],
+    layout: &L,
+    options: Option<PyRef<'_, PyMergeOptions>>,
+) -> PyResult<Py<PyBytes>> {
+    let options = match options {
+        Some(options) => options.to_options()?,
+        None => MergeOptions::default(),
+    };
+    let images: Vec<&[u8]> = images.iter().map(|image| image.as_bytes()).collect();
+    let output = py
+        .detach(|| merge_with_layout(&images, layout, &options))
+        .map_err(|e| MergeError::new_err(e.to_string()))?;
+    Ok(PyBytes::new(py, &output.bytes).unbind())
+}
+
+/// 宫格拼图，返回编码后的图片；fit 为 cover、stretch、contain、contain_blur
+#[pyfunction]
+#[pyo3(signature = (images, options = None, fit = None))]
+fn merge(
+    py: Python<'_>,
+    images: Vec<Bound<'_, PyBytes>>,
+    options: Option<PyRef<'_, PyMergeOptions>>,
+    fit: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let mut layout = GridLayout::default();
+    if let Some(fit) = fit {
+        layout = layout.fit(fit_mode(fit)?);
+    }
+    merge_bytes(py, &images, &layout, options)
+}
+
+/// 瀑布流拼图，返回编码后的图片；fit 为 cover、stretch、contain、contain_blur
+#[pyfunction]
+#[pyo3(signature = (images, options = None, fit = None))]
+fn waterfall(
+    py: Python<'_>,
+    images: Vec<Bound<'_, PyBytes>>,
+    options: Option<PyRef<'_, PyMergeOptions>>,
+    fit: Option<&str>,
+) -> PyResult<Py<PyBytes>> {
+    let mut layout = WaterfallLayout::default();
+    if let Some(fit) = fit {
+        layout = layout.fit(fit_mode(fit)?);
+    }
+    merge_bytes(py, &images, &layout, options)
+}
+
+#[pymodule]
+fn merge_images(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMergeOptions>()?;
+    m.add_function(wrap_pyfunction!(merge, m)?)?;
+    m.add_function(wrap_pyfunction!(waterfall, m)?)?;
+    m.add("MergeError", m.py().get_type::<MergeError>())?;
+    Ok(())
+}
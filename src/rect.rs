@@ -0,0 +1,70 @@
+use std::ops::{BitAnd, BitOr};
+
+/// 矩形区域，(x, y) 为左上角
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn area(&self) -> i32 {
+        self.width * self.height
+    }
+
+    /// 宽或高不大于 0
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    /// (x, y) 是否在矩形内，右边和下边不算
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.x <= x && x < self.x + self.width && self.y <= y && y < self.y + self.height
+    }
+}
+
+/// 交集，不相交时为空矩形
+impl BitAnd for Rect {
+    type Output = Rect;
+
+    fn bitand(self, other: Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        if right <= x || bottom <= y {
+            return Rect::default();
+        }
+        Rect::new(x, y, right - x, bottom - y)
+    }
+}
+
+/// 包含两个矩形的最小矩形，空矩形不参与计算
+impl BitOr for Rect {
+    type Output = Rect;
+
+    fn bitor(self, other: Rect) -> Rect {
+        if self.is_empty() {
+            return other;
+        }
+        if other.is_empty() {
+            return self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect::new(x, y, right - x, bottom - y)
+    }
+}
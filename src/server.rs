@@ -0,0 +1,445 @@
+//! HTTP 服务，需要启用 `server` feature。
+//!
+//! - `GET /health`：健康检查，返回 `ok`
+//! - `POST /merge`：返回拼好的图片，请求体是以下两种之一：
+//!   - `multipart/form-data`：每个文件字段是一张图片，按顺序拼接；文本字段是拼图参数
+//!   - `application/json`：`{"images": ["<base64>", ...], "layout": "waterfall", ...}`
+//!
+//! 拼图参数：`layout`（grid、waterfall、justified）、`fit`、`padding`、`background`（`#rrggbb`）、
+//! `transparent`、`format`（jpeg、png、webp、bmp、tiff）、`quality`、`lossless`、`columns`、
+//! `cell_size`、`width`、`row_height`、`max_bytes`、`crop`、`on_failure`。
+//! 尺寸参数超出范围时返回 400，布局的画布超过 `ServerConfig::max_canvas_pixels` 时返回 422。
+//!
+//! ```no_run
+//! # async fn run() -> std::io::Result<()> {
+//! let app = merge_images::server::router(Default::default());
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+//! axum::serve(listener, app).await
+//! # }
+//! ```
+
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use axum::extract::{DefaultBodyLimit, FromRequest, Multipart, Request, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::Engine;
+use bytes::Bytes;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tokio::sync::Semaphore;
+
+use crate::async_api::merge_holding;
+
+use crate::{
+    CropStrategy, FailurePolicy, FitMode, GridLayout, ImageMeta, JustifiedLayout, Layout,
+    MergeError, MergeOptions, OutputFormat, Placement, WaterfallLayout,
+};
+
+/// 间距的范围（像素）
+const PADDING_RANGE: RangeInclusive<i32> = 0..=1000;
+/// 列数的范围
+const COLUMNS_RANGE: RangeInclusive<i32> = 1..=100;
+/// 格子宽度、画布宽度、行高的范围（像素）
+const SIZE_RANGE: RangeInclusive<i32> = 1..=10_000;
+
+/// 服务参数
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// 请求体的最大字节数，超出时返回 413
+    pub max_body_bytes: usize,
+    /// 每个请求最多的图片数，超出时返回 413
+    pub max_images: usize,
+    /// 同时进行的拼图数量，超出时返回 503
+    pub max_concurrency: usize,
+    /// 画布的最大像素数，布局超出时返回 422
+    pub max_canvas_pixels: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 32 << 20,
+            max_images: 100,
+            max_concurrency: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
+            max_canvas_pixels: 64_000_000,
+        }
+    }
+}
+
+struct AppState {
+    config: ServerConfig,
+    permits: Arc<Semaphore>,
+}
+
+/// 出错时返回 `{"error": "..."}`
+#[derive(Debug)]
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        ApiError(StatusCode::BAD_REQUEST, message.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.1 }));
+        (self.0, body).into_response()
+    }
+}
+
+impl From<MergeError> for ApiError {
+    fn from(e: MergeError) -> Self {
+        let status = match e {
+            MergeError::NoImages
+            | MergeError::Decode { .. }
+            | MergeError::UnsupportedFormat { .. }
+            | MergeError::GifFrame { .. }
+            | MergeError::TooLarge { .. }
+            | MergeError::Layout(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LayoutKind {
+    Grid,
+    Waterfall,
+    Justified,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Fit {
+    Cover,
+    Stretch,
+    Contain,
+    ContainBlur,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Format {
+    Jpeg,
+    Png,
+    Webp,
+    Bmp,
+    Tiff,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Crop {
+    Center,
+    Top,
+    Saliency,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OnFailure {
+    Abort,
+    Skip,
+    Placeholder,
+}
+
+/// 拼图参数，multipart 的文本字段和 JSON 的其他字段都解析为它
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Params {
+    layout: Option<LayoutKind>,
+    fit: Option<Fit>,
+    padding: Option<i32>,
+    background: Option<String>,
+    transparent: bool,
+    format: Option<Format>,
+    quality: Option<i32>,
+    lossless: bool,
+    columns: Option<i32>,
+    cell_size: Option<i32>,
+    width: Option<i32>,
+    row_height: Option<i32>,
+    max_bytes: Option<usize>,
+    crop: Option<Crop>,
+    on_failure: Option<OnFailure>,
+}
+
+/// 检查客户端给出的参数在 range 之内
+fn check_range(name: &str, value: Option<i32>, range: RangeInclusive<i32>) -> Result<(), ApiError> {
+    match value {
+        Some(value) if !range.contains(&value) => Err(ApiError::bad_request(format!(
+            "{} must be within {}~{}",
+            name,
+            range.start(),
+            range.end()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// 检查布局的画布大小，避免客户端的参数让服务分配过大的画布
+struct CanvasLimit<L> {
+    inner: L,
+    max_pixels: u64,
+}
+
+impl<L: Layout> Layout for CanvasLimit<L> {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> crate::Result<Placement> {
+        let placement = self.inner.layout(images, options)?;
+        let (width, height) = placement.canvas;
+        if width.max(0) as u64 * height.max(0) as u64 > self.max_pixels {
+            return Err(MergeError::Layout(format!(
+                "canvas {}x{} exceeds {} pixels",
+                width, height, self.max_pixels
+            )));
+        }
+        Ok(placement)
+    }
+}
+
+/// 解析 `#rrggbb` 或 `rrggbb`
+fn parse_color(s: &str) -> Result<[u8; 3], ApiError> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let invalid = || ApiError::bad_request(format!("invalid background color `{}`", s));
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut rgb = [0; 3];
+    for (i, c) in rgb.iter_mut().enumerate() {
+        *c = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(rgb)
+}
+
+impl Params {
+    fn from_map(map: Map<String, Value>) -> Result<Self, ApiError> {
+        serde_json::from_value(Value::Object(map))
+            .map_err(|e| ApiError::bad_request(format!("invalid parameters: {}", e)))
+    }
+
+    fn format(&self) -> OutputFormat {
+        match self.format.unwrap_or(Format::Jpeg) {
+            Format::Jpeg => OutputFormat::jpeg(self.quality.unwrap_or(95)),
+            Format::Png => OutputFormat::png(),
+            Format::Webp if self.lossless => OutputFormat::webp_lossless(),
+            Format::Webp => OutputFormat::webp(self.quality.unwrap_or(90)),
+            Format::Bmp => OutputFormat::Bmp,
+            Format::Tiff => OutputFormat::Tiff,
+        }
+    }
+
+    fn options(&self) -> Result<MergeOptions, ApiError> {
+        check_range("quality", self.quality, 1..=100)?;
+        check_range("padding", self.padding, PADDING_RANGE)?;
+        check_range("columns", self.columns, COLUMNS_RANGE)?;
+        check_range("cell_size", self.cell_size, SIZE_RANGE)?;
+        check_range("width", self.width, SIZE_RANGE)?;
+        check_range("row_height", self.row_height, SIZE_RANGE)?;
+        let mut builder = MergeOptions::builder()
+            .format(self.format())
+            .transparent(self.transparent);
+        if let Some(padding) = self.padding {
+            builder = builder.padding(padding);
+        }
+        if let Some(background) = &self.background {
+            builder = builder.background(parse_color(background)?);
+        }
+        if let Some(columns) = self.columns {
+            builder = builder.columns(columns);
+        }
+        if let Some(cell_size) = self.cell_size {
+            builder = builder.cell_size(cell_size);
+        }
+        if let Some(max_bytes) = self.max_bytes {
+            builder = builder.max_bytes(max_bytes);
+        }
+        if let Some(crop) = self.crop {
+            builder = builder.crop(match crop {
+                Crop::Center => CropStrategy::Center,
+                Crop::Top => CropStrategy::Top,
+                Crop::Saliency => CropStrategy::Saliency,
+            });
+        }
+        if let Some(on_failure) = self.on_failure {
+            builder = builder.on_failure(match on_failure {
+                OnFailure::Abort => FailurePolicy::Abort,
+                OnFailure::Skip => FailurePolicy::Skip,
+                OnFailure::Placeholder => FailurePolicy::Placeholder,
+            });
+        }
+        Ok(builder.build())
+    }
+
+    fn layout(&self) -> Box<dyn Layout + Send> {
+        let fit = self.fit.map(|fit| match fit {
+            Fit::Cover => FitMode::Cover,
+            Fit::Stretch => FitMode::Stretch,
+            Fit::Contain => FitMode::Contain,
+            Fit::ContainBlur => FitMode::ContainBlur,
+        });
+        match self.layout.unwrap_or(LayoutKind::Grid) {
+            LayoutKind::Grid => {
+                let layout = GridLayout::default();
+                Box::new(fit.map_or(layout, |fit| layout.fit(fit)))
+            }
+            LayoutKind::Waterfall => {
+                let layout = WaterfallLayout::default();
+                Box::new(fit.map_or(layout, |fit| layout.fit(fit)))
+            }
+            LayoutKind::Justified => {
+                let default = JustifiedLayout::default();
+                let layout = JustifiedLayout::new(
+                    self.width.unwrap_or(default.width),
+                    self.row_height.unwrap_or(default.row_height),
+                );
+                Box::new(fit.map_or(layout, |fit| layout.fit(fit)))
+            }
+        }
+    }
+}
+
+/// multipart 的文本字段按 JSON 解析，解析失败时作为字符串，如 `padding=10`、`layout=grid`
+fn field_value(text: String) -> Value {
+    match serde_json::from_str::<Value>(&text) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_))) => value,
+        _ => Value::String(text),
+    }
+}
+
+async fn read_multipart(mut multipart: Multipart) -> Result<(Vec<Bytes>, Params), ApiError> {
+    let multipart_error =
+        |e: axum::extract::multipart::MultipartError| ApiError(e.status(), e.body_text());
+    let mut images = vec![];
+    let mut params = Map::new();
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.file_name().is_some() {
+            images.push(field.bytes().await.map_err(multipart_error)?);
+        } else {
+            let name = field.name().unwrap_or_default().to_string();
+            let text = field.text().await.map_err(multipart_error)?;
+            params.insert(name, field_value(text));
+        }
+    }
+    Ok((images, Params::from_map(params)?))
+}
+
+fn read_json(mut body: Map<String, Value>) -> Result<(Vec<Bytes>, Params), ApiError> {
+    let images = match body.remove("images") {
+        Some(Value::Array(images)) => images,
+        _ => {
+            return Err(ApiError::bad_request(
+                "`images` must be an array of base64 strings",
+            ))
+        }
+    };
+    let images = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            image
+                .as_str()
+                .and_then(|s| base64::engine::general_purpose::STANDARD.decode(s).ok())
+                .map(Bytes::from)
+                .ok_or_else(|| ApiError::bad_request(format!("images[{}] is not valid base64", i)))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((images, Params::from_map(body)?))
+}
+
+/// 输出的 MIME 类型；只有一张图片时原样返回输入，格式不一定是 options.format
+fn content_type(bytes: &[u8], format: &OutputFormat) -> &'static str {
+    use imagesize::ImageType;
+
+    match imagesize::image_type(bytes) {
+        Ok(ImageType::Jpeg) => "image/jpeg",
+        Ok(ImageType::Png) => "image/png",
+        Ok(ImageType::Gif) => "image/gif",
+        Ok(ImageType::Webp) => "image/webp",
+        Ok(ImageType::Bmp) => "image/bmp",
+        Ok(ImageType::Tiff) => "image/tiff",
+        _ => format.mime_type(),
+    }
+}
+
+async fn health() -> &'static str {
+    "ok"
+}
+
+async fn merge(State(state): State<Arc<AppState>>, request: Request) -> Result<Response, ApiError> {
+    let content_type_header = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let (images, params) = if content_type_header.starts_with("multipart/form-data") {
+        let multipart = Multipart::from_request(request, &())
+            .await
+            .map_err(|e| ApiError(e.status(), e.body_text()))?;
+        read_multipart(multipart).await?
+    } else if content_type_header.starts_with("application/json") {
+        let Json(body) = Json::<Map<String, Value>>::from_request(request, &())
+            .await
+            .map_err(|e| ApiError(e.status(), e.body_text()))?;
+        read_json(body)?
+    } else {
+        return Err(ApiError(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "expected multipart/form-data or application/json".to_string(),
+        ));
+    };
+
+    if images.len() > state.config.max_images {
+        return Err(ApiError(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("at most {} images per request", state.config.max_images),
+        ));
+    }
+    let options = params.options()?;
+    let layout = CanvasLimit {
+        inner: params.layout(),
+        max_pixels: state.config.max_canvas_pixels,
+    };
+
+    // 拼图很耗 CPU，超过并发上限时直接拒绝，不排队。
+    // 客户端断开时 future 被丢弃，但拼图要到下一批图片之前才会停止，
+    // 所以许可交给阻塞线程池中的拼图持有，而不是随 future 释放
+    let permit = state.permits.clone().try_acquire_owned().map_err(|_| {
+        ApiError(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too many concurrent requests".to_string(),
+        )
+    })?;
+    let format = options.format;
+    let output = merge_holding(images, layout, options, permit)
+        .await
+        .map_err(|e| {
+            info!("merge failed: {}", e);
+            ApiError::from(e)
+        })?;
+
+    let mime = content_type(&output.bytes, &format);
+    Ok(([(header::CONTENT_TYPE, mime)], output.bytes).into_response())
+}
+
+/// 服务的路由，可以直接交给 `axum::serve`，也可以合并到其他的 `Router` 中
+pub fn router(config: ServerConfig) -> Router {
+    let state = Arc::new(AppState {
+        permits: Arc::new(Semaphore::new(config.max_concurrency)),
+        config: config.clone(),
+    });
+    Router::new()
+        .route("/health", get(health))
+        .route("/merge", post(merge))
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
+        .with_state(state)
+}
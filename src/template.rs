@@ -0,0 +1,369 @@
+//! 用数据描述的布局模板。
+//!
+//! 一个模板包含若干个变体，按照图片数量选用格子数相同的变体。格子的坐标和大小以 `unit` 像素为单位，
+//! 相邻的格子之间再插入 `gap` 像素的间距：格子左边（上边）最长的一串相邻格子有几个，就向右（下）移动几个间距。
+//! 例如下面是内置宫格的 5 图变体，上面两张 900x900，下面三张 600x600：
+//!
+//! ```toml
+//! [[variants]]
+//! canvas = [6, 5]
+//! unit = 300
+//! cells = [
+//!     { x = 0, y = 0, width = 3, height = 3 },
+//!     { x = 3, y = 0, width = 3, height = 3 },
+//!     { x = 0, y = 3, width = 2, height = 2 },
+//!     { x = 2, y = 3, width = 2, height = 2 },
+//!     { x = 4, y = 3, width = 2, height = 2 },
+//! ]
+//! ```
+//!
+//! 启用 `serde` feature 后模板可以序列化，`json`、`toml` feature 分别提供
+//! [`LayoutTemplate::from_json`]、[`LayoutTemplate::from_toml`]。
+
+use crate::prelude::*;
+use crate::{Cell, FitMode, ImageMeta, Layout, MergeOptions, Placement};
+
+/// 模板中的一个格子，坐标和大小以所在变体的 `unit` 为单位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemplateCell {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// 为 None 时使用变体的 fit
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub fit: Option<FitMode>,
+}
+
+impl TemplateCell {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            fit: None,
+        }
+    }
+
+    fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+/// 某个图片数量的布局
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemplateVariant {
+    /// 画布大小 (width, height)，以 unit 为单位，不含间距
+    pub canvas: (i32, i32),
+    /// 一个单位的像素数
+    pub unit: i32,
+    /// 相邻格子之间的间距（像素），为 None 时使用 `MergeOptions::padding`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub gap: Option<i32>,
+    /// 格子默认的填充方式
+    #[cfg_attr(feature = "serde", serde(default = "default_fit"))]
+    pub fit: FitMode,
+    /// 与输入的图片按顺序一一对应
+    pub cells: Vec<TemplateCell>,
+}
+
+#[cfg(feature = "serde")]
+fn default_fit() -> FitMode {
+    FitMode::Cover
+}
+
+/// 每个格子左边（horizontal 为 true 时）或上边最长的一串相邻格子的长度，即它前面的间距数
+fn gap_counts(cells: &[TemplateCell], horizontal: bool) -> Vec<i32> {
+    // (起点, 终点, 另一个方向的起点, 终点)
+    let spans: Vec<_> = cells
+        .iter()
+        .map(|c| {
+            if horizontal {
+                (c.x, c.x + c.width, c.y, c.y + c.height)
+            } else {
+                (c.y, c.y + c.height, c.x, c.x + c.width)
+            }
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..cells.len()).collect();
+    order.sort_by_key(|&i| spans[i].0);
+
+    let mut counts = vec![0; cells.len()];
+    for (k, &i) in order.iter().enumerate() {
+        let (start, _, lo, hi) = spans[i];
+        counts[i] = order[..k]
+            .iter()
+            .filter(|&&j| {
+                let (_, end, other_lo, other_hi) = spans[j];
+                end <= start && other_lo < hi && lo < other_hi
+            })
+            .map(|&j| counts[j] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    counts
+}
+
+impl TemplateVariant {
+    pub fn new(canvas: (i32, i32), unit: i32, cells: Vec<TemplateCell>) -> Self {
+        Self {
+            canvas,
+            unit,
+            gap: None,
+            fit: FitMode::Cover,
+            cells,
+        }
+    }
+
+    /// 检查变体：大小为正、格子都在画布内且互不重叠，像素大小不超出 i32
+    pub fn validate(&self) -> Result<()> {
+        let (width, height) = self.canvas;
+        if self.unit <= 0 || width <= 0 || height <= 0 {
+            return Err(MergeError::Layout(format!(
+                "template canvas {:?} and unit {} must be positive",
+                self.canvas, self.unit
+            )));
+        }
+        if self.gap.is_some_and(|gap| gap < 0) {
+            return Err(MergeError::Layout("template gap is negative".to_string()));
+        }
+        if self.cells.is_empty() {
+            return Err(MergeError::Layout(
+                "template variant has no cells".to_string(),
+            ));
+        }
+        for (i, cell) in self.cells.iter().enumerate() {
+            // 先确认右边和下边不溢出，之后的矩形运算才是安全的
+            let inside = |start: i32, len: i32, end: i32| {
+                start >= 0 && len > 0 && start.checked_add(len).is_some_and(|e| e <= end)
+            };
+            let rect = cell.rect();
+            if !inside(cell.x, cell.width, width) || !inside(cell.y, cell.height, height) {
+                return Err(MergeError::Layout(format!(
+                    "template cell {} {:?} is out of canvas {:?}",
+                    i, rect, self.canvas
+                )));
+            }
+            if let Some(j) = self.cells[..i]
+                .iter()
+                .position(|other| !(other.rect() & rect).is_empty())
+            {
+                return Err(MergeError::Layout(format!(
+                    "template cells {} and {} overlap",
+                    j, i
+                )));
+            }
+        }
+        self.check_pixels(self.gap.unwrap_or(0))
+    }
+
+    /// 检查间距为 gap 时像素坐标不会溢出；格子都在画布内，只需要检查画布
+    fn check_pixels(&self, gap: i32) -> Result<()> {
+        let pixels = |len: i32, gaps: Vec<i32>| {
+            let gaps = gaps.into_iter().max().unwrap_or(0);
+            len.checked_mul(self.unit)?
+                .checked_add(gaps.checked_mul(gap)?)
+        };
+        let (width, height) = self.canvas;
+        match (
+            pixels(width, gap_counts(&self.cells, true)),
+            pixels(height, gap_counts(&self.cells, false)),
+        ) {
+            (Some(_), Some(_)) => Ok(()),
+            _ => Err(MergeError::Layout(format!(
+                "template canvas {:?} with unit {} and gap {} is too large",
+                self.canvas, self.unit, gap
+            ))),
+        }
+    }
+
+    /// 按照间距 gap 计算像素坐标，调用前应先 [`validate`](Self::validate)
+    pub fn placement(&self, gap: i32) -> Placement {
+        let xs = gap_counts(&self.cells, true);
+        let ys = gap_counts(&self.cells, false);
+        let unit = self.unit;
+        let cells = self
+            .cells
+            .iter()
+            .zip(xs.iter().zip(&ys))
+            .map(|(cell, (gx, gy))| Cell {
+                rect: Rect::new(
+                    cell.x * unit + gx * gap,
+                    cell.y * unit + gy * gap,
+                    cell.width * unit,
+                    cell.height * unit,
+                ),
+                fit: cell.fit.unwrap_or(self.fit),
+            })
+            .collect();
+        let (width, height) = self.canvas;
+        let canvas = (
+            width * unit + xs.iter().max().unwrap_or(&0) * gap,
+            height * unit + ys.iter().max().unwrap_or(&0) * gap,
+        );
+        Placement { canvas, cells }
+    }
+}
+
+/// 内置宫格的一个变体：(画布, 格子的 (x, y, 边长))，单位为 300 像素
+type GridVariant = ((i32, i32), &'static [(i32, i32, i32)]);
+
+const GRID_VARIANTS: [GridVariant; 9] = [
+    ((6, 6), &[(0, 0, 6)]),
+    ((6, 3), &[(0, 0, 3), (3, 0, 3)]),
+    // 1 + 2
+    ((6, 9), &[(0, 0, 6), (0, 6, 3), (3, 6, 3)]),
+    // 2x2，先列后行
+    ((6, 6), &[(0, 0, 3), (0, 3, 3), (3, 0, 3), (3, 3, 3)]),
+    // 2 + 3
+    (
+        (6, 5),
+        &[(0, 0, 3), (3, 0, 3), (0, 3, 2), (2, 3, 2), (4, 3, 2)],
+    ),
+    // 3x2
+    (
+        (6, 4),
+        &[
+            (0, 0, 2),
+            (2, 0, 2),
+            (4, 0, 2),
+            (0, 2, 2),
+            (2, 2, 2),
+            (4, 2, 2),
+        ],
+    ),
+    // 2 + 2 + 3
+    (
+        (6, 8),
+        &[
+            (0, 0, 3),
+            (3, 0, 3),
+            (0, 3, 3),
+            (3, 3, 3),
+            (0, 6, 2),
+            (2, 6, 2),
+            (4, 6, 2),
+        ],
+    ),
+    // 2 + 3 + 3
+    (
+        (6, 7),
+        &[
+            (0, 0, 3),
+            (3, 0, 3),
+            (0, 3, 2),
+            (2, 3, 2),
+            (4, 3, 2),
+            (0, 5, 2),
+            (2, 5, 2),
+            (4, 5, 2),
+        ],
+    ),
+    // 九宫图
+    (
+        (6, 6),
+        &[
+            (0, 0, 2),
+            (2, 0, 2),
+            (4, 0, 2),
+            (0, 2, 2),
+            (2, 2, 2),
+            (4, 2, 2),
+            (0, 4, 2),
+            (2, 4, 2),
+            (4, 4, 2),
+        ],
+    ),
+];
+
+/// 布局模板：按图片数量选用格子数相同的变体
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutTemplate {
+    pub variants: Vec<TemplateVariant>,
+}
+
+impl LayoutTemplate {
+    /// 检查所有的变体，且同一个图片数量只有一个变体
+    pub fn new(variants: Vec<TemplateVariant>) -> Result<Self> {
+        let template = Self { variants };
+        template.validate()?;
+        Ok(template)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (i, variant) in self.variants.iter().enumerate() {
+            variant.validate()?;
+            let n = variant.cells.len();
+            if self.variants[..i].iter().any(|v| v.cells.len() == n) {
+                return Err(MergeError::Layout(format!(
+                    "more than one template variant for {} images",
+                    n
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// n 张图片使用的变体
+    pub fn variant(&self, n: usize) -> Option<&TemplateVariant> {
+        self.variants.iter().find(|v| v.cells.len() == n)
+    }
+
+    /// 内置的 1~9 图宫格，格子是 1800、900、600 像素的正方形
+    pub fn grid() -> Self {
+        let variants = GRID_VARIANTS
+            .iter()
+            .map(|(canvas, cells)| {
+                let cells = cells
+                    .iter()
+                    .map(|&(x, y, size)| TemplateCell::new(x, y, size, size))
+                    .collect();
+                TemplateVariant::new(*canvas, 300, cells)
+            })
+            .collect();
+        Self { variants }
+    }
+
+    /// 从 JSON 加载并检查模板
+    #[cfg(feature = "json")]
+    pub fn from_json(s: &str) -> Result<Self> {
+        let template: Self =
+            serde_json::from_str(s).map_err(|e| MergeError::Layout(e.to_string()))?;
+        template.validate()?;
+        Ok(template)
+    }
+
+    /// 从 TOML 加载并检查模板
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: &str) -> Result<Self> {
+        let template: Self = toml::from_str(s).map_err(|e| MergeError::Layout(e.to_string()))?;
+        template.validate()?;
+        Ok(template)
+    }
+}
+
+impl Layout for LayoutTemplate {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement> {
+        let variant = self.variant(images.len()).ok_or_else(|| {
+            MergeError::Layout(format!("no template variant for {} images", images.len()))
+        })?;
+        variant.validate()?;
+        options.check_no_output_size("template")?;
+        if variant.gap.is_none() {
+            options.check_layout()?;
+        }
+        let gap = variant.gap.unwrap_or(options.padding);
+        variant.check_pixels(gap)?;
+        Ok(variant.placement(gap))
+    }
+}
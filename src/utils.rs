@@ -1,82 +1,175 @@
-use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::animation::{self, AnimatedTile};
+use crate::backend::{self, Filter};
+use crate::orientation::Orientation;
 use crate::prelude::*;
+use crate::{
+    Cell, CropStrategy, FailurePolicy, FitMode, ImageMeta, Layout, MergeOptions, MergeOutput,
+    Placement,
+};
 
-/// 把图片处理成 (width, height) 大小
-fn process_image(im: Mat, square: bool, width: i32, height: i32) -> Result<Mat> {
+/// 无法读出大小的图片在使用占位图时按照正方形布局
+const PLACEHOLDER_SIZE: (i32, i32) = (100, 100);
+
+/// 从图片中选取要放进 (width, height) 格子的区域
+fn select_roi(
+    im: &Image,
+    fit: FitMode,
+    width: i32,
+    height: i32,
+    strategy: &CropStrategy,
+) -> Result<Rect> {
+    match fit {
+        FitMode::Cover => strategy.select(im, width, height),
+        FitMode::Stretch | FitMode::Contain | FitMode::ContainBlur => {
+            let (cols, rows) = Active::size(im);
+            Ok(Rect::new(0, 0, cols, rows))
+        }
+    }
+}
+
+/// 把图片上的 roi 区域处理成 (width, height) 大小
+fn process_image(im: &Image, roi: Rect, fit: FitMode, width: i32, height: i32) -> Result<Image> {
     debug!(
-        "processing image into size ({}, {}), square = {}",
-        width, height, square
+        "processing image into size ({}, {}), roi = {:?}, fit = {:?}",
+        width, height, roi, fit
     );
-    let roi = if square {
-        // 从中间选择正方形区域
-        let min_size = im.rows().min(im.cols());
-        if min_size == im.rows() {
-            // 高度短，横向裁剪
-            let x = (im.cols() - min_size) / 2;
-            Rect::new(x, 0, min_size, min_size)
-        } else {
-            let y = (im.rows() - min_size) / 2;
-            Rect::new(0, y, min_size, min_size)
+    match fit {
+        FitMode::Contain => return contain(im, roi, width, height, false),
+        FitMode::ContainBlur => return contain(im, roi, width, height, true),
+        FitMode::Cover | FitMode::Stretch => {}
+    }
+
+    let resize_result = Active::resize(im, roi, width, height, Filter::Linear);
+    let resized = match resize_result {
+        Ok(resized) => resized,
+        Err(e) => {
+            warn!("failed to resize image: {}", e);
+            return Err(e.into());
         }
-    } else {
-        Rect::new(0, 0, im.cols(), im.rows())
     };
 
-    let im = Mat::roi(&im, roi)?;
+    debug!("image resized");
+    Ok(resized)
+}
 
-    let mut resized = Mat::default();
+/// 把 roi 区域保持比例缩放到 (width, height) 以内并居中；四周留下的空白在 blur 为 true 时
+/// 用模糊放大的原图填充，否则是透明的，贴到画布上之后露出背景
+fn contain(im: &Image, roi: Rect, width: i32, height: i32, blur: bool) -> Result<Image> {
+    let (cols, rows) = (roi.width, roi.height);
+    let scale = (width as f64 / cols as f64).min(height as f64 / rows as f64);
+    let inner_width = ((cols as f64 * scale).round() as i32).clamp(1, width);
+    let inner_height = ((rows as f64 * scale).round() as i32).clamp(1, height);
+    let inner = Active::resize(im, roi, inner_width, inner_height, Filter::Linear)?;
 
-    let resize_result = imgproc::resize(
-        &im,
-        &mut resized,
-        cv_core::Size::new(width, height),
-        0.,
-        0.,
-        imgproc::INTER_LINEAR,
+    let mut cell = if blur {
+        // 在缩小的图上模糊再放大，比直接在格子大小的图上模糊快得多
+        let fill = CropStrategy::Center.select(im, width, height)?;
+        let small = Active::resize(
+            im,
+            fill,
+            (width / 16).max(1),
+            (height / 16).max(1),
+            Filter::Area,
+        )?;
+        let blurred = Active::gaussian_blur(&small, 2.)?;
+        let (small_width, small_height) = Active::size(&blurred);
+        Active::resize(
+            &blurred,
+            Rect::new(0, 0, small_width, small_height),
+            width,
+            height,
+            Filter::Linear,
+        )?
+    } else {
+        Active::filled(width, height, [0, 0, 0], Some(0))?
+    };
+    let rect = Rect::new(
+        (width - inner_width) / 2,
+        (height - inner_height) / 2,
+        inner_width,
+        inner_height,
     );
-    match resize_result {
-        Ok(_) => {}
-        Err(e) => {
-            warn!("failed to resize image: {}", e);
-            return Err(e);
+    Active::paste(&inner, &mut cell, rect)?;
+    Ok(cell)
+}
+
+/// 解码时可以缩小的倍数（1、2、4、8），缩小之后仍然不小于格子需要的大小
+fn reduce_factor(source_size: Option<(i32, i32)>, cell: &Cell) -> i32 {
+    let (width, height) = match source_size {
+        Some((width, height)) if width > 0 && height > 0 => (width, height),
+        _ => return 1,
+    };
+    let fx = width as f64 / cell.rect.width as f64;
+    let fy = height as f64 / cell.rect.height as f64;
+    // 原图比格子大多少倍
+    let ratio = match cell.fit {
+        // 裁剪或拉伸后两个方向都要覆盖格子
+        FitMode::Cover | FitMode::Stretch => fx.min(fy),
+        // 只要较长的一边放得下
+        FitMode::Contain | FitMode::ContainBlur => fx.max(fy),
+    };
+    [8, 4, 2]
+        .iter()
+        .copied()
+        .find(|factor| *factor as f64 <= ratio)
+        .unwrap_or(1)
+}
+
+/// 把解码后图片上的区域换算到原图的坐标；解码时可能缩小过
+fn to_source_rect(roi: Rect, decoded: (i32, i32), source: Option<(i32, i32)>) -> Rect {
+    match source {
+        Some((width, height)) if (width, height) != decoded => {
+            let fx = width as f64 / decoded.0 as f64;
+            let fy = height as f64 / decoded.1 as f64;
+            Rect::new(
+                (roi.x as f64 * fx).round() as i32,
+                (roi.y as f64 * fy).round() as i32,
+                (roi.width as f64 * fx).round() as i32,
+                (roi.height as f64 * fy).round() as i32,
+            )
         }
+        _ => roi,
     }
+}
 
-    debug!("image resized");
-    Ok(resized)
+/// 原图过大时至少缩小的倍数
+fn size_limit_factor(width: usize, height: usize) -> i32 {
+    match width.max(height) {
+        size if size > 8000 => 8,
+        size if size > 3000 => 4,
+        _ => 1,
+    }
 }
 
-/// 对 imdecode 简单地包装了一下，避免在遇到尺寸过大的图像时内存溢出。
-pub fn imdecode_wrapped(bytes: &[u8]) -> Result<Mat> {
-    let src = Mat::from_slice(bytes).map_err(|e| {
-        info!("Mat::from_slice error: {}", e);
-        debug!("{:?}", e);
-        e
-    })?;
-    let im_decode_result = match imagesize::blob_size(bytes) {
+/// 解码并按照 EXIF 方向摆正。至少缩小为 1/factor，尺寸过大时缩小得更多，避免内存溢出；
+/// keep_alpha 为 true 时保留透明通道
+fn decode_image(bytes: &[u8], factor: i32, keep_alpha: bool) -> backend::Result<Image> {
+    let factor = match imagesize::blob_size(bytes) {
         Ok(imagesize::ImageSize { width, height }) => {
-            let flag = match width.max(height) {
-                size if size > 8000 => {
-                    info!("size too big: ({}x{}), shrink to 1/8", width, height);
-                    imgcodecs::IMREAD_REDUCED_COLOR_8
-                }
-                size if size > 3000 => {
-                    info!("size too big: ({}x{}), shrink to 1/4", width, height);
-                    imgcodecs::IMREAD_REDUCED_COLOR_4
-                }
-                _ => imgcodecs::IMREAD_COLOR,
-            };
-            imgcodecs::imdecode(&src, flag)
+            let limit = size_limit_factor(width, height);
+            if limit > factor {
+                info!(
+                    "size too big: ({}x{}), shrink to 1/{}",
+                    width, height, limit
+                );
+            } else if factor > 1 {
+                debug!(
+                    "({}x{}) is larger than needed, shrink to 1/{}",
+                    width, height, factor
+                );
+            }
+            factor.max(limit)
         }
         Err(e) => {
             warn!("cannot get image size in advance: {:?}", e);
-            imgcodecs::imdecode(&src, imgcodecs::IMREAD_COLOR)
+            factor
         }
     };
-    let im = im_decode_result?;
+    let im = Active::decode(bytes, factor, keep_alpha)?;
 
-    let (width, height) = (im.cols(), im.rows());
+    let (width, height) = Active::size(&im);
     let im = match width.max(height) {
         #[cfg(debug_assertions)]
         size if size > 8000 => {
@@ -85,14 +178,12 @@ pub fn imdecode_wrapped(bytes: &[u8]) -> Result<Mat> {
         #[cfg(not(debug_assertions))]
         size if size > 8000 => {
             error!("表现不一致：大小还是超过 8000；继续缩放为 1/8");
-            let mut output = Mat::default();
-            imgproc::resize(
+            let output = Active::resize(
                 &im,
-                &mut output,
-                cv_core::Size::new(0, 0),
-                1. / 8.,
-                1. / 8.,
-                imgproc::INTER_LINEAR,
+                Rect::new(0, 0, width, height),
+                (width / 8).max(1),
+                (height / 8).max(1),
+                Filter::Linear,
             )?;
             std::mem::drop(im);
             output
@@ -100,98 +191,527 @@ pub fn imdecode_wrapped(bytes: &[u8]) -> Result<Mat> {
         _ => im,
     };
 
-    Ok(im)
+    Orientation::read(bytes).apply(im)
+}
+
+/// 从文件头判断图片是否带透明通道，只识别 PNG 和 WebP
+fn has_alpha(bytes: &[u8]) -> bool {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // IHDR 中的 color type：4 为灰度 + alpha，6 为 RGBA
+        if matches!(bytes.get(25), Some(4) | Some(6)) {
+            return true;
+        }
+        // 调色板图片在 IDAT 之前有 tRNS 块时也有透明
+        let mut pos = 8;
+        while pos + 8 <= bytes.len() {
+            let len =
+                u32::from_be_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]]);
+            match &bytes[pos + 4..pos + 8] {
+                b"tRNS" => return true,
+                b"IDAT" => return false,
+                _ => pos += 12 + len as usize,
+            }
+        }
+        false
+    } else if bytes.len() >= 30 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        match &bytes[12..16] {
+            // 扩展格式的 flags 中有 alpha 位
+            b"VP8X" => bytes[20] & 0x10 != 0,
+            // 无损格式头部的 alpha_is_used 位
+            b"VP8L" => bytes[24] & 0x10 != 0,
+            _ => false,
+        }
+    } else {
+        false
+    }
 }
 
-fn read_image_or_first_frame(bytes: &[u8]) -> Result<Mat> {
-    match imagesize::image_type(&bytes) {
+/// 解码图片，带透明通道的图片解码为 4 通道；GIF 只取第一帧。
+/// 除了 GIF 之外至少缩小为 1/factor
+fn read_image_or_first_frame(index: usize, bytes: &[u8], factor: i32) -> Result<Image> {
+    let decode_error = |source| MergeError::Decode { index, source };
+    match imagesize::image_type(bytes) {
         Ok(imagesize::ImageType::Gif) => {
-            // get first frame
-            use tempfile::NamedTempFile;
-            let mut file = NamedTempFile::new()
-                .map_err(|e| Error::new(-1, format!("failed to open tempfile: {}", e)))?;
-
-            file.write_all(bytes).unwrap();
-            file.flush().unwrap();
-            let path = file.path().as_os_str().to_string_lossy();
-            let path = path.as_ref();
-            let mut mat = Mat::default();
-            let mut gif = opencv::videoio::VideoCapture::from_file(path, 0)?;
-            if gif.read(&mut mat)? {
-                info!("read frame from gif success");
-                Ok(mat)
-            } else {
-                Err(Error::new(-2, "read frame from gif failed".to_string()))
+            let decoded = animation::decode_gif(bytes, 0)
+                .map_err(|source| MergeError::Decode { index, source })?;
+            match decoded.frames.first() {
+                Some(rgba) => {
+                    info!("read frame from gif success");
+                    Active::from_rgba(rgba, decoded.width, decoded.height).map_err(decode_error)
+                }
+                None => Err(MergeError::GifFrame { index }),
+            }
+        }
+        image_type => {
+            let keep_alpha = has_alpha(bytes);
+            if keep_alpha {
+                debug!("the {}-th image has alpha channel", index);
             }
+            decode_image(bytes, factor, keep_alpha).map_err(|source| match image_type {
+                Ok(_) => decode_error(source),
+                // 文件头无法识别，解码也失败了
+                Err(_) => MergeError::UnsupportedFormat { index },
+            })
         }
-        _ => imdecode_wrapped(bytes),
     }
 }
 
-pub(crate) fn merge_<T: AsRef<[u8]>>(
+/// 从文件头中读取图片大小，按照 EXIF 方向摆正之后的
+fn image_meta(index: usize, bytes: &[u8]) -> ImageMeta {
+    let size = match imagesize::blob_size(bytes) {
+        Ok(imagesize::ImageSize { width, height }) => {
+            Some(Orientation::read(bytes).apply_to_size((width as i32, height as i32)))
+        }
+        Err(e) => {
+            info!("cannot get size of the {}-th image: {:?}", index, e);
+            None
+        }
+    };
+    ImageMeta { index, size }
+}
+
+pub(crate) fn merge_<T: AsRef<[u8]>, L: Layout + ?Sized>(
     image_bytes: &[T],
-    gen_poses: impl Fn(&[T]) -> Result<((i32, i32), Vec<Rect>)>,
-    square: bool,
-) -> Result<Vec<u8>> {
+    layout: &L,
+    options: &MergeOptions,
+) -> Result<MergeOutput> {
+    merge_cancellable(image_bytes, layout, options, &AtomicBool::new(false))
+}
+
+/// 与 `merge_` 相同，但每画一批图片之前检查 cancelled，为 true 时返回 `MergeError::Cancelled`
+pub(crate) fn merge_cancellable<T: AsRef<[u8]>, L: Layout + ?Sized>(
+    image_bytes: &[T],
+    layout: &L,
+    options: &MergeOptions,
+    cancelled: &AtomicBool,
+) -> Result<MergeOutput> {
     debug!("merging {} images", image_bytes.len());
     if image_bytes.is_empty() {
-        return Err(Error::new(1, "no images".to_string()));
+        return Err(MergeError::NoImages);
     }
     if image_bytes.len() == 1 {
-        return Ok(image_bytes[0].as_ref().to_vec());
-    }
-
-    // 生成画布
-    let ((width, height), poses) = gen_poses(image_bytes)?;
-    debug!("canvas size: {} x {}", width, height);
-    let canvas = Mat::new_rows_cols_with_default(
-        height,
-        width,
-        cv_core::CV_8UC3,
-        cv_core::Scalar::all(255.),
-    )?;
-    debug!("canvas = {:?}", canvas);
-
-    for (idx, (bytes, pos)) in image_bytes.iter().zip(poses).enumerate() {
-        let im = read_image_or_first_frame(bytes.as_ref()).map_err(|e| {
-            info!("error imdecode the {}-th bytes (0 based index): {}", idx, e);
-            debug!("{:?}", e);
-            e
-        })?;
-        info!("image size: {:?}", im.size()?);
-
-        debug!("pos = {:?}", pos);
-        let im = match process_image(im, square, pos.width, pos.height) {
-            Ok(im) => im,
-            Err(e) => {
-                info!("failed to process the {}-th image: {}. continue", idx, e);
-                debug!("cause: {:?}", e);
-                continue;
+        let bytes = image_bytes[0].as_ref();
+        let meta = image_meta(0, bytes);
+        match options.max_bytes {
+            Some(max_bytes) if bytes.len() > max_bytes => {
+                // 超出大小限制，只能重新编码
+                info!("single image exceeds {} bytes, re-encode", max_bytes);
+                let im = read_image_or_first_frame(0, bytes, 1)?;
+                let (width, height) = Active::size(&im);
+                let mut canvas = new_canvas(width, height, options)?;
+                Active::paste(&im, &mut canvas, Rect::new(0, 0, width, height))?;
+                let encoded = options.format.encode_within(&canvas, options.max_bytes)?;
+                let full = Rect::new(0, 0, width, height);
+                return Ok(MergeOutput {
+                    bytes: encoded.bytes,
+                    quality: encoded.quality,
+                    scale: encoded.scale,
+                    canvas: (width, height),
+                    cells: vec![Some(full)],
+                    crops: vec![Some(to_source_rect(full, (width, height), meta.size))],
+                    skipped: vec![],
+                    placeholders: vec![],
+                    frames: 1,
+                });
+            }
+            _ => {
+                let (width, height) = meta.size.unwrap_or((0, 0));
+                let full = Rect::new(0, 0, width, height);
+                return Ok(MergeOutput {
+                    bytes: bytes.to_vec(),
+                    quality: None,
+                    scale: 1.,
+                    canvas: (width, height),
+                    cells: vec![Some(full)],
+                    crops: vec![Some(full)],
+                    skipped: vec![],
+                    placeholders: vec![],
+                    frames: 1,
+                });
             }
+        }
+    }
+
+    let mut metas: Vec<ImageMeta> = image_bytes
+        .iter()
+        .enumerate()
+        .map(|(idx, bytes)| image_meta(idx, bytes.as_ref()))
+        .collect();
+
+    let mut skipped: Vec<usize> = vec![];
+    let mut last_error = None;
+    // FailurePolicy::Skip 时每跳过一张图片就重新布局一次
+    'layout: loop {
+        let active: Vec<ImageMeta> = metas
+            .iter()
+            .filter(|meta| !skipped.contains(&meta.index))
+            .copied()
+            .collect();
+        if active.is_empty() {
+            return Err(last_error.unwrap_or(MergeError::NoImages));
+        }
+
+        // 生成画布
+        let placement = match layout.layout(&active, options) {
+            Ok(placement) => placement,
+            Err(e) => match (e.index(), options.on_failure) {
+                (Some(index), FailurePolicy::Skip) if !skipped.contains(&index) => {
+                    info!("layout failed for the {}-th image: {}. skip", index, e);
+                    skipped.push(index);
+                    last_error = Some(e);
+                    continue 'layout;
+                }
+                (Some(index), FailurePolicy::Placeholder) if metas[index].size.is_none() => {
+                    info!(
+                        "layout failed for the {}-th image: {}. use placeholder",
+                        index, e
+                    );
+                    metas[index].size = Some(PLACEHOLDER_SIZE);
+                    continue 'layout;
+                }
+                _ => return Err(e),
+            },
         };
+        check_placement(&placement, active.len())?;
+        let (width, height) = placement.canvas;
+        debug!("canvas size: {} x {}", width, height);
+        let mut canvas = new_canvas(width, height, options)?;
+
+        let mut cells = vec![None; image_bytes.len()];
+        let mut crops = vec![None; image_bytes.len()];
+        let mut placeholders = vec![];
+        let mut animated = vec![];
+        let jobs: Vec<Job> = active
+            .iter()
+            .zip(&placement.cells)
+            .map(|(meta, cell)| Job {
+                index: meta.index,
+                bytes: image_bytes[meta.index].as_ref(),
+                cell,
+                source_size: meta.size,
+            })
+            .collect();
+        // 每批图片先（并行地）解码缩放，再按顺序画到画布上，结果与逐张处理相同
+        for batch in jobs.chunks(batch_size()) {
+            if cancelled.load(Ordering::Relaxed) {
+                info!("merge cancelled");
+                return Err(MergeError::Cancelled);
+            }
+            for (job, rendered) in batch.iter().zip(render_tiles(batch, options)) {
+                let (idx, rect) = (job.index, job.cell.rect);
+                match rendered {
+                    Ok((crop, tile)) => {
+                        match tile {
+                            Tile::Static(im) => {
+                                debug!(
+                                    "image copy: src = {:?}, pos = {:?}",
+                                    Active::size(&im),
+                                    rect
+                                );
+                                Active::paste(&im, &mut canvas, rect)?;
+                            }
+                            Tile::Animated(tile) => animated.push(tile),
+                        }
+                        cells[idx] = Some(rect);
+                        crops[idx] = Some(crop);
+                    }
+                    Err(e) => match options.on_failure {
+                        FailurePolicy::Abort => return Err(e),
+                        FailurePolicy::Skip => {
+                            info!("failed to render the {}-th image: {}. skip", idx, e);
+                            skipped.push(idx);
+                            last_error = Some(e);
+                            continue 'layout;
+                        }
+                        FailurePolicy::Placeholder => {
+                            info!(
+                                "failed to render the {}-th image: {}. use placeholder",
+                                idx, e
+                            );
+                            draw_placeholder(&mut canvas, rect)?;
+                            cells[idx] = Some(rect);
+                            placeholders.push(idx);
+                        }
+                    },
+                }
+            }
+        }
+
+        if cancelled.load(Ordering::Relaxed) {
+            info!("merge cancelled before encoding");
+            return Err(MergeError::Cancelled);
+        }
+        skipped.sort_unstable();
+        if let Some(animation) = &options.animation {
+            let (bytes, frames) = animation::encode(&canvas, &animated, animation)?;
+            // 动图不降低质量也不缩小，超出限制直接报错
+            if let Some(max_bytes) = options.max_bytes {
+                if bytes.len() > max_bytes {
+                    return Err(MergeError::TooLarge { max_bytes });
+                }
+            }
+            return Ok(MergeOutput {
+                bytes,
+                quality: None,
+                scale: 1.,
+                canvas: (width, height),
+                cells,
+                crops,
+                skipped,
+                placeholders,
+                frames,
+            });
+        }
+        let encoded = options.format.encode_within(&canvas, options.max_bytes)?;
+        return Ok(MergeOutput {
+            bytes: encoded.bytes,
+            quality: encoded.quality,
+            scale: encoded.scale,
+            canvas: (width, height),
+            cells,
+            crops,
+            skipped,
+            placeholders,
+            frames: 1,
+        });
+    }
+}
+
+/// 生成填充了背景色的画布；输出透明背景时带透明通道
+fn new_canvas(width: i32, height: i32, options: &MergeOptions) -> Result<Image> {
+    let alpha = if options.transparent_canvas() {
+        Some(0)
+    } else {
+        None
+    };
+    Ok(Active::filled(width, height, options.background, alpha)?)
+}
+
+/// 检查布局结果：格子数量与图片数量一致，且都在画布内
+fn check_placement(placement: &Placement, n: usize) -> Result<()> {
+    if placement.cells.len() != n {
+        return Err(MergeError::Layout(format!(
+            "layout returned {} cells for {} images",
+            placement.cells.len(),
+            n
+        )));
+    }
+    let (width, height) = placement.canvas;
+    let canvas_rect = Rect::new(0, 0, width, height);
+    for (idx, cell) in placement.cells.iter().enumerate() {
+        if (cell.rect & canvas_rect) != cell.rect || cell.rect.width <= 0 || cell.rect.height <= 0 {
+            return Err(MergeError::Layout(format!(
+                "cell of the {}-th image is out of canvas: {:?}",
+                idx, cell.rect
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 要放进一个格子的图片
+struct Job<'a> {
+    index: usize,
+    bytes: &'a [u8],
+    cell: &'a Cell,
+    source_size: Option<(i32, i32)>,
+}
+
+/// 缩放好、等待画到画布上的图片
+enum Tile {
+    Static(Image),
+    /// 输出动图时的多帧 GIF
+    Animated(AnimatedTile),
+}
+
+/// 同时解码的图片数量，限制内存占用
+#[cfg(feature = "rayon")]
+fn batch_size() -> usize {
+    rayon::current_num_threads().max(1)
+}
+
+#[cfg(not(feature = "rayon"))]
+fn batch_size() -> usize {
+    1
+}
+
+/// 在 rayon 的线程池中解码缩放一批图片，结果与 jobs 的顺序一致
+#[cfg(feature = "rayon")]
+fn render_tiles(jobs: &[Job], options: &MergeOptions) -> Vec<Result<(Rect, Tile)>> {
+    use rayon::prelude::*;
+    jobs.par_iter()
+        .map(|job| render_tile(job, options))
+        .collect()
+}
 
-        let mut roi = Mat::roi(&canvas, pos)?;
-        debug!("image copy: src = {:?}, roi = {:?}", im, roi);
+#[cfg(not(feature = "rayon"))]
+fn render_tiles(jobs: &[Job], options: &MergeOptions) -> Vec<Result<(Rect, Tile)>> {
+    jobs.iter().map(|job| render_tile(job, options)).collect()
+}
 
-        im.copy_to(&mut roi)?;
+/// 解码第 index 张图片并缩放到格子的大小，返回原图中被使用的区域；
+/// 输出动图且这张图片是多帧的 GIF 时，返回它的所有帧
+fn render_tile(job: &Job, options: &MergeOptions) -> Result<(Rect, Tile)> {
+    let Job {
+        index,
+        bytes,
+        cell,
+        source_size,
+    } = *job;
+    let pos = cell.rect;
+    let process_error = |e| match e {
+        MergeError::Backend(source) => MergeError::Process { index, source },
+        e => e,
+    };
+    if let (Some(animation), Ok(imagesize::ImageType::Gif)) =
+        (&options.animation, imagesize::image_type(bytes))
+    {
+        let decoded = animation::decode_gif(bytes, animation.max_duration_ms)
+            .map_err(|source| MergeError::Decode { index, source })?;
+        if decoded.frames.len() > 1 {
+            info!(
+                "the {}-th image is animated, {} frames",
+                index,
+                decoded.frames.len()
+            );
+            let mut frames = Vec::with_capacity(decoded.frames.len());
+            let mut roi = None;
+            for rgba in &decoded.frames {
+                let im = Active::from_rgba(rgba, decoded.width, decoded.height)
+                    .map_err(|source| MergeError::Decode { index, source })?;
+                // 所有帧使用第一帧选出的区域，避免画面抖动
+                let frame_roi = match roi {
+                    Some(roi) => roi,
+                    None => {
+                        let first = select_roi(&im, cell.fit, pos.width, pos.height, &options.crop)
+                            .map_err(process_error)?;
+                        roi = Some(first);
+                        first
+                    }
+                };
+                let im = process_image(&im, frame_roi, cell.fit, pos.width, pos.height)
+                    .map_err(process_error)?;
+                frames.push(im);
+            }
+            let roi = roi.unwrap_or_default();
+            let tile = AnimatedTile {
+                rect: pos,
+                frames,
+                delays: decoded.delays,
+            };
+            let decoded = (decoded.width, decoded.height);
+            return Ok((
+                to_source_rect(roi, decoded, source_size),
+                Tile::Animated(tile),
+            ));
+        }
     }
 
-    let mut buf = Vector::new();
-    let flags = Vector::new();
-    imgcodecs::imencode(".jpg", &canvas, &mut buf, &flags)?;
+    let factor = reduce_factor(source_size, cell);
+    let im = read_image_or_first_frame(index, bytes, factor).map_err(|e| {
+        info!(
+            "error imdecode the {}-th bytes (0 based index): {}",
+            index, e
+        );
+        debug!("{:?}", e);
+        e
+    })?;
+    let decoded = Active::size(&im);
+    info!("image size: {:?}", decoded);
+
+    debug!("pos = {:?}", pos);
+    let roi =
+        select_roi(&im, cell.fit, pos.width, pos.height, &options.crop).map_err(process_error)?;
+    let im = process_image(&im, roi, cell.fit, pos.width, pos.height).map_err(process_error)?;
+    Ok((to_source_rect(roi, decoded, source_size), Tile::Static(im)))
+}
+
+/// 在格子里画一个灰色的占位图，中间是一个破损图片的图标
+fn draw_placeholder(canvas: &mut Image, rect: Rect) -> Result<()> {
+    let fill = [200, 200, 200];
+    let ink = [128, 128, 128];
+    Active::fill_rect(canvas, rect, fill)?;
 
-    Ok(buf.to_vec())
+    // 图标：一个相框，右上角缺了一块，中间一道裂痕
+    let size = rect.width.min(rect.height) / 3;
+    if size < 6 {
+        return Ok(());
+    }
+    let thickness = (size / 20).max(1);
+    let (x, y) = (
+        rect.x + (rect.width - size) / 2,
+        rect.y + (rect.height - size) / 2,
+    );
+    let point = |dx: i32, dy: i32| (x + dx, y + dy);
+    let outline = [
+        point(0, 0),
+        point(size * 2 / 3, 0),
+        point(size / 2, size / 3),
+        point(size * 2 / 3, size * 2 / 3),
+        point(size / 2, size),
+        point(0, size),
+        point(0, 0),
+    ];
+    let right = [
+        point(size * 5 / 6, 0),
+        point(size, 0),
+        point(size, size),
+        point(size * 2 / 3, size),
+        point(size * 5 / 6, size * 2 / 3),
+        point(size * 2 / 3, size / 3),
+        point(size * 5 / 6, 0),
+    ];
+    for pair in outline.windows(2).chain(right.windows(2)) {
+        Active::draw_line(canvas, pair[0], pair[1], ink, thickness)?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Read;
 
-    const F: &str = "./test-data/e09ca4a57584181ce573e45079b524ff3859b9fb.jpg";
+    fn cell(width: i32, height: i32, fit: FitMode) -> Cell {
+        Cell {
+            rect: Rect::new(0, 0, width, height),
+            fit,
+        }
+    }
+
+    #[test]
+    fn test_reduce_factor_cover() {
+        // 2560x1440 放进 300x300：宽 8.5 倍，高 4.8 倍，裁剪和拉伸都按较短的一边
+        for fit in [FitMode::Cover, FitMode::Stretch] {
+            assert_eq!(reduce_factor(Some((2560, 1440)), &cell(300, 300, fit)), 4);
+            assert_eq!(reduce_factor(Some((2560, 1440)), &cell(100, 100, fit)), 8);
+            assert_eq!(reduce_factor(Some((2560, 1440)), &cell(800, 800, fit)), 1);
+        }
+    }
 
+    #[test]
+    fn test_reduce_factor_contain() {
+        // 完整放下时按较长的一边
+        for fit in [FitMode::Contain, FitMode::ContainBlur] {
+            assert_eq!(reduce_factor(Some((2560, 1440)), &cell(300, 300, fit)), 8);
+            assert_eq!(reduce_factor(Some((2560, 1440)), &cell(800, 800, fit)), 2);
+            assert_eq!(reduce_factor(Some((2560, 1440)), &cell(2000, 2000, fit)), 1);
+        }
+    }
+
+    #[test]
+    fn test_reduce_factor_unknown_size() {
+        let cell = cell(100, 100, FitMode::Cover);
+        assert_eq!(reduce_factor(None, &cell), 1);
+        assert_eq!(reduce_factor(Some((0, 1440)), &cell), 1);
+    }
+
+    #[cfg(feature = "opencv")]
     #[test]
     fn test_imdecode_wrapped() {
+        use opencv::prelude::*;
+        use std::io::Read;
+
+        const F: &str = "./test-data/e09ca4a57584181ce573e45079b524ff3859b9fb.jpg";
         let im = opencv::imgcodecs::imread(F, opencv::imgcodecs::IMREAD_REDUCED_COLOR_8).unwrap();
         assert_eq!(im.cols(), 1440);
         assert_eq!(im.rows(), 2048);
@@ -200,8 +720,7 @@ mod tests {
         let mut buf = vec![];
         f.read_to_end(&mut buf).unwrap();
 
-        let im = imdecode_wrapped(&buf).unwrap();
-        assert_eq!(im.cols(), 1440);
-        assert_eq!(im.rows(), 2048);
+        let im = decode_image(&buf, 1, false).unwrap();
+        assert_eq!(Active::size(&im), (1440, 2048));
     }
 }
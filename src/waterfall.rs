@@ -1,15 +1,15 @@
 use crate::prelude::*;
 use crate::utils;
-use crate::PAD;
-use imagesize::blob_size;
+use crate::{Cell, FitMode, ImageMeta, Layout, MergeOptions, MergeOutput, Placement};
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 
-fn image_poses<T: AsRef<[u8]>>(image_bytes: &[T]) -> Result<((i32, i32), Vec<Rect>)> {
-    debug!("generating image poses for {} images", image_bytes.len());
+fn image_poses(images: &[ImageMeta], options: &MergeOptions) -> Result<((i32, i32), Vec<Rect>)> {
+    debug!("generating image poses for {} images", images.len());
+    let pad = options.padding;
     let mut ans = vec![];
 
-    let (columns, per_size) = match image_bytes.len() {
+    let (columns, per_size) = match images.len() {
         0..=9 => (2, 800),
         10..=16 => (3, 500),
         17..=25 => (4, 400),
@@ -17,45 +17,87 @@ fn image_poses<T: AsRef<[u8]>>(image_bytes: &[T]) -> Result<((i32, i32), Vec<Rec
         37..=49 => (6, 300),
         _ => (7, 300),
     };
+    let columns = options.columns.unwrap_or(columns);
+    let per_size = options.cell_size.unwrap_or(per_size);
     // 贪心, heap => (row, col(index))
     let mut heap = BinaryHeap::new();
     for i in 0..columns as usize {
         heap.push(Reverse((0, i)));
     }
     let mut image_height = 0;
-    for image_byte in image_bytes {
-        let size = blob_size(image_byte.as_ref()).map_err(|e| {
-            info!("{:?}", e);
-            Error::new(
-                -1,
-                format!("failed to get image size thru imagesize crate: {}", e),
-            )
-        })?;
-        let resized_height = per_size * size.height as i32 / size.width as i32;
-        // SAFETY: heap 一定不空
+    for image in images {
+        let (width, height) = image
+            .size
+            .ok_or(MergeError::UnsupportedFormat { index: image.index })?;
+        if width <= 0 || height <= 0 {
+            return Err(MergeError::UnsupportedFormat { index: image.index });
+        }
+        // 很宽的图片缩放后至少保留 1 像素高
+        let resized_height = (per_size * height / width).max(1);
+        // SAFETY: columns 已检查为正，heap 一定不空
         let Reverse((row, col_index)) = heap.pop().unwrap();
         // 这个图占的位置：rows: (row, row+resized_height)
-        let x = (per_size + PAD) * col_index as i32;
+        let x = (per_size + pad) * col_index as i32;
         let y = row;
         ans.push(Rect::new(x, y, per_size, resized_height));
         image_height = image_height.max(y + resized_height);
-        heap.push(Reverse((y + resized_height + PAD, col_index)));
+        heap.push(Reverse((y + resized_height + pad, col_index)));
     }
 
-    let width = per_size * columns + PAD * (columns - 1);
+    let width = per_size * columns + pad * (columns - 1);
     let height = image_height;
 
     Ok(((width, height), ans))
 }
 
-pub fn merge<T: AsRef<[u8]>>(image_bytes: &[T]) -> Result<Vec<u8>> {
-    debug!("merging {} images", image_bytes.len());
-    if image_bytes.is_empty() {
-        return Err(Error::new(1, "no images".to_string()));
+/// 瀑布流布局：固定宽度的若干列，每张图片放到当前最短的一列
+#[derive(Debug, Clone, Copy)]
+pub struct WaterfallLayout {
+    /// 图片如何填充格子；格子的比例与图片一致，默认直接缩放
+    pub fit: FitMode,
+}
+
+impl Default for WaterfallLayout {
+    fn default() -> Self {
+        Self {
+            fit: FitMode::Stretch,
+        }
+    }
+}
+
+impl WaterfallLayout {
+    pub fn fit(mut self, fit: FitMode) -> Self {
+        self.fit = fit;
+        self
     }
-    if image_bytes.len() == 1 {
-        return Ok(image_bytes[0].as_ref().to_vec());
+}
+
+impl Layout for WaterfallLayout {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement> {
+        options.check_layout()?;
+        options.check_no_output_size("waterfall")?;
+        let (canvas, rects) = image_poses(images, options)?;
+        let cells = rects
+            .into_iter()
+            .map(|rect| Cell {
+                rect,
+                fit: self.fit,
+            })
+            .collect();
+        Ok(Placement { canvas, cells })
     }
+}
+
+/// 返回一张瀑布流拼图，格式为 jpg；需要其他格式时使用 `merge_with`
+pub fn merge<T: AsRef<[u8]>>(image_bytes: &[T]) -> Result<Vec<u8>> {
+    merge_with(image_bytes, &MergeOptions::default()).map(|output| output.bytes)
+}
 
-    utils::merge_(image_bytes, image_poses, false)
+/// 按照 `options` 生成瀑布流拼图，格式由 `options.format` 决定；
+/// 除了图片本身还会返回每张输入图片的位置，见 [`MergeOutput`]
+pub fn merge_with<T: AsRef<[u8]>>(
+    image_bytes: &[T],
+    options: &MergeOptions,
+) -> Result<MergeOutput> {
+    utils::merge_(image_bytes, &WaterfallLayout::default(), options)
 }
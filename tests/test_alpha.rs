@@ -0,0 +1,61 @@
+use std::fs::File;
+use std::io::*;
+
+use image::{DynamicImage, GenericImageView};
+use merge_images::{merge_with, MergeOptions, OutputFormat};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn decode(bytes: &[u8]) -> DynamicImage {
+    image::load_from_memory(bytes).unwrap()
+}
+
+#[test]
+fn test_alpha_blend_onto_background() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("alpha.png");
+    let f2 = data("1.png");
+    let options = MergeOptions::builder()
+        .background([0, 255, 0])
+        .format(OutputFormat::png())
+        .build();
+    let out_im = merge_with(&[f1, f2], &options).unwrap().bytes;
+    let im = decode(&out_im);
+    assert_eq!(im.color().channel_count(), 3);
+    // 透明的角落露出背景色，中间是不透明的红色圆
+    assert_eq!(im.get_pixel(5, 5).0, [0, 255, 0, 255]);
+    assert_eq!(im.get_pixel(450, 450).0, [255, 0, 0, 255]);
+
+    let mut output = File::create("output-alpha.png").unwrap();
+    output.write_all(&out_im).unwrap();
+}
+
+#[test]
+fn test_transparent_canvas() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("alpha.png");
+    let f2 = data("1.png");
+    let options = MergeOptions::builder()
+        .transparent(true)
+        .format(OutputFormat::png())
+        .build();
+    let out_im = merge_with(&[f1, f2], &options).unwrap().bytes;
+    let im = decode(&out_im);
+    assert_eq!(im.color().channel_count(), 4);
+    assert_eq!(im.get_pixel(5, 5).0[3], 0);
+    assert_eq!(im.get_pixel(450, 450).0, [255, 0, 0, 255]);
+    // 第二张图片不透明
+    assert_eq!(im.get_pixel(1300, 450).0[3], 255);
+
+    // JPEG 不支持透明，仍然使用背景色
+    let options = MergeOptions::builder().transparent(true).build();
+    let out_im = merge_with(&[data("alpha.png"), data("1.png")], &options)
+        .unwrap()
+        .bytes;
+    assert_eq!(decode(&out_im).color().channel_count(), 3);
+}
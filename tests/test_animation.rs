@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{merge_with, waterfall_with, AnimationOptions, MergeError, MergeOptions};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+/// 数一下 GIF 里的帧
+fn count_frames(bytes: &[u8]) -> usize {
+    let mut decoder = gif::DecodeOptions::new().read_info(bytes).unwrap();
+    let mut n = 0;
+    while decoder.read_next_frame().unwrap().is_some() {
+        n += 1;
+    }
+    n
+}
+
+#[test]
+fn test_merge_animated() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("A.gif");
+    let f2 = data("1.png");
+    let f3 = data("A.gif");
+    let options = MergeOptions::builder()
+        .animation(AnimationOptions::default())
+        .build();
+    let output = merge_with(&[f1, f2, f3], &options).unwrap();
+    assert!(output.bytes.starts_with(b"GIF89a"));
+    assert!(output.frames > 1);
+    assert_eq!(count_frames(&output.bytes), output.frames);
+
+    let mut file = File::create("output-animated.gif").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+#[test]
+fn test_merge_animated_caps() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("A.gif");
+    let f2 = data("4.jpg");
+    let animation = AnimationOptions {
+        max_frames: 5,
+        max_duration_ms: 1000,
+        ..AnimationOptions::default()
+    };
+    let options = MergeOptions::builder().animation(animation).build();
+    let output = waterfall_with(&[f1, f2], &options).unwrap();
+    assert!(output.frames <= 5);
+    assert_eq!(count_frames(&output.bytes), output.frames);
+}
+
+#[test]
+fn test_merge_static_without_animation() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("A.gif");
+    let f2 = data("1.png");
+    let output = merge_with(&[f1, f2], &MergeOptions::default()).unwrap();
+    assert!(output.bytes.starts_with(b"\xff\xd8"));
+    assert_eq!(output.frames, 1);
+}
+
+/// 逻辑屏幕为 screen，每一帧都是放在 (left, top) 的 2x2 白色方块，delays 是各帧的延迟
+fn crafted_gif(screen: (u16, u16), (left, top): (u16, u16), delays: &[u16]) -> Vec<u8> {
+    let mut bytes = vec![];
+    {
+        let mut encoder =
+            gif::Encoder::new(&mut bytes, screen.0, screen.1, &[0, 0, 0, 255, 255, 255]).unwrap();
+        for &delay in delays {
+            let frame = gif::Frame {
+                left,
+                top,
+                width: 2,
+                height: 2,
+                delay,
+                dispose: gif::DisposalMethod::Background,
+                buffer: vec![1; 4].into(),
+                ..Default::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+    }
+    bytes
+}
+
+#[test]
+fn test_merge_gif_frame_outside_screen() {
+    pretty_env_logger::try_init().ok();
+    // 帧在逻辑屏幕的右边之外
+    let gif = crafted_gif((10, 10), (20, 0), &[10; 3]);
+    let options = MergeOptions::builder()
+        .animation(AnimationOptions::default())
+        .build();
+    let output = merge_with(&[gif.clone(), data("1.png")], &options).unwrap();
+    assert!(output.bytes.starts_with(b"GIF89a"));
+    merge_with(&[gif, data("1.png")], &MergeOptions::default()).unwrap();
+}
+
+#[test]
+fn test_merge_gif_huge_screen() {
+    // 文件很小，但逻辑屏幕声明为 65535x65535
+    let gif = crafted_gif((65535, 65535), (0, 0), &[10]);
+    let e = merge_with(&[gif, data("1.png")], &MergeOptions::default()).unwrap_err();
+    assert!(matches!(e, MergeError::Decode { index: 0, .. }), "{:?}", e);
+}
+
+#[test]
+fn test_merge_gif_long_delay() {
+    pretty_env_logger::try_init().ok();
+    // 只输出一帧，这一帧的时长 655360ms 超过了 GIF 延迟的上限
+    let gif = crafted_gif((10, 10), (0, 0), &[65535, 1]);
+    let animation = AnimationOptions {
+        max_frames: 1,
+        max_duration_ms: u32::MAX,
+        ..AnimationOptions::default()
+    };
+    let options = MergeOptions::builder().animation(animation).build();
+    let output = merge_with(&[gif, data("1.png")], &options).unwrap();
+    let mut decoder = gif::DecodeOptions::new()
+        .read_info(&output.bytes[..])
+        .unwrap();
+    let frame = decoder.read_next_frame().unwrap().unwrap();
+    assert_eq!(frame.delay, u16::MAX);
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "tokio")]
+
+use std::fs::File;
+use std::io::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use merge_images::{
+    merge_async, merge_with, merge_with_layout_async, waterfall_async, waterfall_with,
+    FailurePolicy, GridLayout, ImageMeta, Layout, MergeOptions, MergeOutput, OutputFormat,
+    Placement, Result,
+};
+use tokio::sync::{mpsc, oneshot};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn images() -> Vec<Vec<u8>> {
+    ["1.png", "2.png", "3.png", "4.jpg"]
+        .iter()
+        .map(|name| data(name))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_async_same_as_sync() {
+    pretty_env_logger::try_init().ok();
+    let options = MergeOptions::builder().format(OutputFormat::png()).build();
+    let owned: Vec<Bytes> = images().into_iter().map(Bytes::from).collect();
+
+    let output = merge_async(owned.clone(), options.clone()).await.unwrap();
+    assert_eq!(output.bytes, merge_with(&images(), &options).unwrap().bytes);
+
+    let output = waterfall_async(owned, options.clone()).await.unwrap();
+    assert_eq!(
+        output.bytes,
+        waterfall_with(&images(), &options).unwrap().bytes
+    );
+
+    let mut file = File::create("output-async.png").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+/// 第一次布局时通知 started 并等待 gate，之后直接使用宫格；记录被调用的次数，
+/// 被丢弃（即阻塞线程池中的拼图结束）时通知 dropped
+struct GatedLayout {
+    calls: Arc<AtomicUsize>,
+    started: mpsc::UnboundedSender<()>,
+    gate: Mutex<Option<std::sync::mpsc::Receiver<()>>>,
+    dropped: Option<oneshot::Sender<()>>,
+}
+
+impl Layout for GatedLayout {
+    fn layout(&self, images: &[ImageMeta], options: &MergeOptions) -> Result<Placement> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if let Some(gate) = self.gate.lock().unwrap().take() {
+            self.started.send(()).unwrap();
+            gate.recv().ok();
+        }
+        GridLayout::default().layout(images, options)
+    }
+}
+
+impl Drop for GatedLayout {
+    fn drop(&mut self) {
+        if let Some(dropped) = self.dropped.take() {
+            dropped.send(()).ok();
+        }
+    }
+}
+
+/// 第一张图片的文件头完整但数据被截断，能布局但解码失败；
+/// FailurePolicy::Skip 时解码它之后会跳过它重新布局
+fn images_with_truncated_first() -> Vec<Bytes> {
+    let mut images: Vec<Bytes> = images().into_iter().map(Bytes::from).collect();
+    images[0] = images[0].slice(..1000);
+    images
+}
+
+/// 返回拼图结果、布局被调用的次数；cancel 为 true 时在第一次布局时丢弃 future
+async fn run_gated(cancel: bool) -> (Option<merge_images::Result<MergeOutput>>, usize) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let (started_tx, mut started) = mpsc::unbounded_channel();
+    let (gate_tx, gate) = std::sync::mpsc::channel();
+    let (dropped_tx, dropped) = oneshot::channel();
+    let layout = GatedLayout {
+        calls: calls.clone(),
+        started: started_tx,
+        gate: Mutex::new(Some(gate)),
+        dropped: Some(dropped_tx),
+    };
+    let options = MergeOptions::builder()
+        .on_failure(FailurePolicy::Skip)
+        .build();
+    let task = tokio::spawn(merge_with_layout_async(
+        images_with_truncated_first(),
+        layout,
+        options,
+    ));
+
+    started.recv().await.unwrap();
+    let result = if cancel {
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+        gate_tx.send(()).unwrap();
+        None
+    } else {
+        gate_tx.send(()).unwrap();
+        Some(task.await.unwrap())
+    };
+    // 等到阻塞线程池中的拼图结束
+    tokio::time::timeout(Duration::from_secs(60), dropped)
+        .await
+        .unwrap()
+        .unwrap();
+    (result, calls.load(Ordering::SeqCst))
+}
+
+#[tokio::test]
+async fn test_async_drop_cancels() {
+    pretty_env_logger::try_init().ok();
+    // 不取消时解码第一张图片失败，跳过它重新布局
+    let (result, calls) = run_gated(false).await;
+    assert_eq!(result.unwrap().unwrap().skipped, vec![0]);
+    assert_eq!(calls, 2);
+
+    // future 在布局时被丢弃，拼图在处理第一批图片之前停止，不会再重新布局
+    let (_, calls) = run_gated(true).await;
+    assert_eq!(calls, 1);
+}
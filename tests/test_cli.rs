@@ -0,0 +1,200 @@
+#![cfg(feature = "cli")]
+
+use std::fs::File;
+use std::io::*;
+use std::process::{Command, Stdio};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn cli() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_merge-images"))
+}
+
+#[test]
+fn test_cli_files_to_file() {
+    let status = cli()
+        .args([
+            "./test-data/1.png",
+            "./test-data/[23].png",
+            "-p",
+            "10",
+            "-b",
+            "#000000",
+            "-o",
+            "output-cli.png",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // 根据扩展名输出 PNG
+    let buf = std::fs::read("output-cli.png").unwrap();
+    assert_eq!(image::guess_format(&buf).unwrap(), image::ImageFormat::Png);
+    let im = image::load_from_memory(&buf).unwrap().to_rgb8();
+    // 1 + 2 的宫格，上下两行之间的间距是背景色
+    assert_eq!(im.dimensions(), (1810, 2710));
+    assert_eq!(im.get_pixel(0, 1805).0, [0, 0, 0]);
+}
+
+#[test]
+fn test_cli_stdin_to_stdout() {
+    let mut child = cli()
+        .args([
+            "-",
+            "./test-data/2.png",
+            "-f",
+            "jpeg",
+            "-q",
+            "80",
+            "-o",
+            "-",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&data("1.png"))
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        image::guess_format(&output.stdout).unwrap(),
+        image::ImageFormat::Jpeg
+    );
+
+    let output = cli()
+        .args(["./test-data/no-such-file.png", "-o", "-"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_cli_hero() {
+    let status = cli()
+        .args([
+            "./test-data/[1-3].png",
+            "-l",
+            "hero",
+            "--hero",
+            "1",
+            "--hero-position",
+            "left",
+            "--width",
+            "900",
+            "-o",
+            "output-cli-hero.jpg",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let buf = std::fs::read("output-cli-hero.jpg").unwrap();
+    let im = image::load_from_memory(&buf).unwrap();
+    assert_eq!(im.height(), 900);
+}
+
+#[test]
+fn test_cli_template() {
+    // 左边一张大图，右边上下两张小图
+    let templates = [
+        (
+            "output-cli-template.toml",
+            r#"
+[[variants]]
+canvas = [3, 2]
+unit = 200
+gap = 0
+cells = [
+    { x = 0, y = 0, width = 2, height = 2 },
+    { x = 2, y = 0, width = 1, height = 1 },
+    { x = 2, y = 1, width = 1, height = 1 },
+]
+"#,
+        ),
+        (
+            "output-cli-template.json",
+            r#"{"variants": [{"canvas": [3, 2], "unit": 200, "gap": 0, "cells": [
+                {"x": 0, "y": 0, "width": 2, "height": 2},
+                {"x": 2, "y": 0, "width": 1, "height": 1},
+                {"x": 2, "y": 1, "width": 1, "height": 1}
+            ]}]}"#,
+        ),
+    ];
+    for (path, template) in templates {
+        std::fs::write(path, template).unwrap();
+        let output = cli()
+            .args(["./test-data/[1-3].png", "--template", path, "-o", "-"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let im = image::load_from_memory(&output.stdout).unwrap();
+        assert_eq!((im.width(), im.height()), (600, 400));
+    }
+
+    // 模板不能与 --layout 同时使用
+    let output = cli()
+        .args([
+            "./test-data/[1-3].png",
+            "--template",
+            "output-cli-template.toml",
+            "-l",
+            "waterfall",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cli_output_size() {
+    let output = cli()
+        .args([
+            "./test-data/[1-4].*",
+            "-p",
+            "10",
+            "--output-width",
+            "605",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let im = image::load_from_memory(&output.stdout).unwrap();
+    assert_eq!((im.width(), im.height()), (605, 605));
+
+    let output = cli()
+        .args(["./test-data/[1-3].png", "--max-dimension", "900", "-o", "-"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let im = image::load_from_memory(&output.stdout).unwrap();
+    assert!(im.width().max(im.height()) <= 900);
+
+    // 只有宫格支持输出尺寸
+    let output = cli()
+        .args([
+            "./test-data/[1-3].png",
+            "-l",
+            "waterfall",
+            "--max-dimension",
+            "900",
+            "-o",
+            "-",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{merge_with, CropStrategy, MergeOptions, Rect};
+#[cfg(feature = "opencv")]
+use merge_images::{
+    merge_with_layout, FaceDetector, LayoutTemplate, TemplateCell, TemplateVariant,
+};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+/// tall-text.png 是 200x600 的白图，只有 y 在 420~560 之间有棋盘格
+fn crop_of(strategy: CropStrategy) -> Rect {
+    let f1 = data("tall-text.png");
+    let f2 = data("3.png");
+    let options = MergeOptions::builder().crop(strategy).build();
+    let output = merge_with(&[f1, f2], &options).unwrap();
+    output.crops[0].unwrap()
+}
+
+#[test]
+fn test_crop_center_and_top() {
+    pretty_env_logger::try_init().ok();
+    assert_eq!(crop_of(CropStrategy::Center), Rect::new(0, 200, 200, 200));
+    assert_eq!(crop_of(CropStrategy::Top), Rect::new(0, 80, 200, 200));
+}
+
+#[test]
+fn test_crop_saliency() {
+    pretty_env_logger::try_init().ok();
+    let crop = crop_of(CropStrategy::Saliency);
+    assert_eq!((crop.width, crop.height), (200, 200));
+    // 棋盘格完整地落在裁剪区域内
+    assert!(crop.y <= 420 && crop.y + crop.height >= 560, "{:?}", crop);
+
+    let f1 = data("tall-text.png");
+    let f2 = data("3.png");
+    let options = MergeOptions::builder().crop(CropStrategy::Saliency).build();
+    let out_im = merge_with(&[f1, f2], &options).unwrap().bytes;
+    let mut output = File::create("output-crop-saliency.jpg").unwrap();
+    output.write_all(&out_im).unwrap();
+}
+
+#[cfg(feature = "opencv")]
+#[test]
+fn test_face_detector_invalid_cascade() {
+    pretty_env_logger::try_init().ok();
+    assert!(FaceDetector::from_bytes(b"not a cascade").is_err());
+}
+
+/// 2.png 是 689x814 的合影，人脸都在 y 为 100~320 之间；
+/// 裁剪成 2:1 的横条时居中会切掉人脸，按人脸裁剪则要保留它们
+#[cfg(feature = "opencv")]
+#[test]
+fn test_crop_face() {
+    pretty_env_logger::try_init().ok();
+    let variant = TemplateVariant::new(
+        (4, 1),
+        200,
+        vec![TemplateCell::new(0, 0, 2, 1), TemplateCell::new(2, 0, 2, 1)],
+    );
+    let layout = LayoutTemplate::new(vec![variant]).unwrap();
+    let detector = FaceDetector::frontal_face().unwrap();
+    let options = MergeOptions::builder()
+        .crop(CropStrategy::Face(detector))
+        .build();
+    let output = merge_with_layout(&[data("2.png"), data("3.png")], &layout, &options).unwrap();
+    let crop = output.crops[0].unwrap();
+    assert_eq!(crop.width, 689);
+    assert!(crop.y <= 100 && crop.y + crop.height >= 320, "{:?}", crop);
+
+    let mut output_file = File::create("output-crop-face.jpg").unwrap();
+    output_file.write_all(&output.bytes).unwrap();
+}
@@ -0,0 +1,45 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{merge_with, merge_with_layout, FitMode, GridLayout, MergeOptions, Rect};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_reduced_decode_crops() {
+    pretty_env_logger::try_init().ok();
+    // 2560x1440 的图片放进 100x100 的格子，解码时缩小为 1/8，裁剪区域仍是原图坐标
+    let f1 = data("6.png");
+    let f2 = data("3.png");
+    // cell_size 只影响大于 9 图的宫格
+    let mut images = vec![&f1];
+    images.extend(vec![&f2; 9]);
+    let options = MergeOptions::builder().cell_size(100).build();
+    let output = merge_with(&images, &options).unwrap();
+    assert_eq!(output.cells[0], Some(Rect::new(0, 0, 100, 100)));
+    assert_eq!(output.crops[0], Some(Rect::new(560, 0, 1440, 1440)));
+    assert_eq!(output.crops[1], Some(Rect::new(0, 0, 340, 340)));
+
+    let im = image::load_from_memory(&output.bytes).unwrap();
+    assert_eq!((im.width(), im.height()), (430, 320));
+
+    let mut file = File::create("output-reduced-decode.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+#[test]
+fn test_reduced_decode_contain() {
+    pretty_env_logger::try_init().ok();
+    // Contain 按较长的一边计算缩小倍数
+    let f1 = data("5.png");
+    let f2 = data("6.png");
+    let layout = GridLayout::default().fit(FitMode::Contain);
+    let output = merge_with_layout(&[&f1, &f2], &layout, &MergeOptions::default()).unwrap();
+    assert_eq!(output.crops[0], Some(Rect::new(0, 0, 1080, 2340)));
+    assert_eq!(output.crops[1], Some(Rect::new(0, 0, 2560, 1440)));
+}
@@ -0,0 +1,47 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{merge, waterfall, MergeError};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_error_no_images() {
+    let empty: &[&[u8]] = &[];
+    assert!(matches!(merge(empty), Err(MergeError::NoImages)));
+}
+
+#[test]
+fn test_error_unsupported_format() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let garbage = b"definitely not an image".to_vec();
+    let f3 = data("3.png");
+    let err = merge(&[&f1, &garbage, &f3]).unwrap_err();
+    assert!(
+        matches!(err, MergeError::UnsupportedFormat { index: 1 }),
+        "{}",
+        err
+    );
+    assert_eq!(err.index(), Some(1));
+
+    // 瀑布流在布局时就需要图片大小
+    let err = waterfall(&[&f1, &f3, &garbage]).unwrap_err();
+    assert_eq!(err.index(), Some(2));
+}
+
+#[test]
+fn test_error_decode() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("4.jpg");
+    // 文件头完整，但数据被截断
+    let truncated = f1[..f1.len() / 2].to_vec();
+    let err = merge(&[&f2, &truncated]).unwrap_err();
+    assert_eq!(err.index(), Some(1), "{}", err);
+}
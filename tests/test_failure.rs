@@ -0,0 +1,87 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{merge_with, waterfall_with, FailurePolicy, MergeError, MergeOptions};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn options(policy: FailurePolicy) -> MergeOptions {
+    MergeOptions::builder().on_failure(policy).build()
+}
+
+#[test]
+fn test_failure_abort() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let garbage = b"not an image".to_vec();
+    let f3 = data("3.png");
+    let err = merge_with(&[&f1, &garbage, &f3], &options(FailurePolicy::Abort)).unwrap_err();
+    assert_eq!(err.index(), Some(1));
+}
+
+#[test]
+fn test_failure_skip_reflows() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let garbage = b"not an image".to_vec();
+    let f3 = data("3.png");
+    let f4 = data("4.jpg");
+    let output = merge_with(&[&f1, &garbage, &f3, &f4], &options(FailurePolicy::Skip)).unwrap();
+    assert_eq!(output.skipped, vec![1]);
+    assert!(output.placeholders.is_empty());
+    assert_eq!(output.cells[1], None);
+    // 按照 3 张图片重新布局
+    let expected = merge_with(&[&f1, &f3, &f4], &MergeOptions::default()).unwrap();
+    assert_eq!(output.canvas, expected.canvas);
+    assert_eq!(output.cells[3], expected.cells[2]);
+
+    let mut file = File::create("output-failure-skip.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+#[test]
+fn test_failure_skip_all() {
+    pretty_env_logger::try_init().ok();
+    let garbage = b"not an image".to_vec();
+    let err = merge_with(&[&garbage, &garbage], &options(FailurePolicy::Skip)).unwrap_err();
+    assert!(
+        matches!(err, MergeError::UnsupportedFormat { .. }),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_failure_placeholder() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let garbage = b"not an image".to_vec();
+    let f3 = data("3.png");
+    let f4 = data("4.jpg");
+    let output = merge_with(
+        &[&f1, &garbage, &f3, &f4],
+        &options(FailurePolicy::Placeholder),
+    )
+    .unwrap();
+    assert!(output.skipped.is_empty());
+    assert_eq!(output.placeholders, vec![1]);
+    assert!(output.cells[1].is_some());
+    assert_eq!(output.crops[1], None);
+
+    let mut file = File::create("output-failure-placeholder.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+
+    // 瀑布流读不出大小时按正方形占位
+    let output = waterfall_with(
+        &[&f1, &garbage, &f3, &f4],
+        &options(FailurePolicy::Placeholder),
+    )
+    .unwrap();
+    let cell = output.cells[1].unwrap();
+    assert_eq!(cell.width, cell.height);
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::*;
+use std::ptr;
+
+use merge_images::ffi::*;
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn buffers(images: &[Vec<u8>]) -> Vec<MergeImagesBuffer> {
+    images
+        .iter()
+        .map(|image| MergeImagesBuffer {
+            data: image.as_ptr(),
+            len: image.len(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_ffi_merge() {
+    pretty_env_logger::try_init().ok();
+    let images = vec![data("1.png"), data("2.png"), data("3.png")];
+    let inputs = buffers(&images);
+
+    let mut result = unsafe { merge_images_grid(inputs.as_ptr(), inputs.len()) };
+    assert_eq!(result.code, MERGE_IMAGES_OK);
+    assert!(result.message.is_null());
+    let bytes = unsafe { std::slice::from_raw_parts(result.data, result.len) };
+    assert_eq!(bytes, &merge_images::merge(&images).unwrap()[..]);
+    unsafe { merge_images_free(&mut result) };
+    assert!(result.data.is_null());
+
+    let mut result = unsafe { merge_images_waterfall(inputs.as_ptr(), inputs.len()) };
+    assert_eq!(result.code, MERGE_IMAGES_OK);
+    let bytes = unsafe { std::slice::from_raw_parts(result.data, result.len) };
+    assert_eq!(bytes, &merge_images::waterfall(&images).unwrap()[..]);
+    let mut file = File::create("output-ffi.jpg").unwrap();
+    file.write_all(bytes).unwrap();
+    unsafe { merge_images_free(&mut result) };
+    // 重复释放是安全的
+    unsafe { merge_images_free(&mut result) };
+    unsafe { merge_images_free(ptr::null_mut()) };
+}
+
+#[test]
+fn test_ffi_errors() {
+    let mut result = unsafe { merge_images_grid(ptr::null(), 0) };
+    assert_eq!(result.code, MERGE_IMAGES_NO_IMAGES);
+    assert!(result.data.is_null());
+    unsafe { merge_images_free(&mut result) };
+
+    let mut result = unsafe { merge_images_grid(ptr::null(), 2) };
+    assert_eq!(result.code, MERGE_IMAGES_INVALID_ARGUMENT);
+    unsafe { merge_images_free(&mut result) };
+
+    let images = vec![b"not an image".to_vec(), data("1.png")];
+    let inputs = buffers(&images);
+    let mut result = unsafe { merge_images_waterfall(inputs.as_ptr(), inputs.len()) };
+    assert_eq!(result.code, MERGE_IMAGES_DECODE_ERROR);
+    let message = unsafe { CStr::from_ptr(result.message) };
+    assert!(message.to_str().unwrap().contains("0-th image"));
+    unsafe { merge_images_free(&mut result) };
+    assert!(result.message.is_null());
+}
+
+/// 构建时生成的头文件，见 build.rs
+const GENERATED_HEADER: &str = include_str!(concat!(env!("OUT_DIR"), "/merge_images.h"));
+
+/// 修改 src/ffi.rs 后用 `MERGE_IMAGES_UPDATE_HEADER=1 cargo test --features ffi --test test_ffi`
+/// 更新仓库中的头文件
+#[test]
+fn test_ffi_header_is_up_to_date() {
+    let path = "./include/merge_images.h";
+    if std::env::var_os("MERGE_IMAGES_UPDATE_HEADER").is_some() {
+        std::fs::write(path, GENERATED_HEADER).unwrap();
+    }
+    let checked_in = std::fs::read_to_string(path).unwrap();
+    assert!(
+        checked_in == GENERATED_HEADER,
+        "{} is out of date, rerun with MERGE_IMAGES_UPDATE_HEADER=1",
+        path
+    );
+}
@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::*;
+
+use image::RgbImage;
+use merge_images::{merge_with_layout, FitMode, GridLayout, MergeOptions, OutputFormat, Rect};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn decode(bytes: &[u8]) -> RgbImage {
+    image::load_from_memory(bytes).unwrap().to_rgb8()
+}
+
+#[test]
+fn test_fit_contain() {
+    pretty_env_logger::try_init().ok();
+    // 1080x2340 的长截图放进 900x900 的格子，左右留白
+    let f1 = data("5.png");
+    let f2 = data("3.png");
+    let options = MergeOptions::builder()
+        .background([255, 0, 255])
+        .format(OutputFormat::png())
+        .build();
+    let layout = GridLayout::default().fit(FitMode::Contain);
+    let output = merge_with_layout(&[&f1, &f2], &layout, &options).unwrap();
+    assert_eq!(output.crops[0], Some(Rect::new(0, 0, 1080, 2340)));
+    let im = decode(&output.bytes);
+    assert_eq!(im.get_pixel(5, 450).0, [255, 0, 255]);
+    assert_eq!(im.get_pixel(894, 450).0, [255, 0, 255]);
+
+    let mut file = File::create("output-fit-contain.png").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+#[test]
+fn test_fit_contain_blur() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("5.png");
+    let f2 = data("3.png");
+    let options = MergeOptions::builder()
+        .background([255, 0, 255])
+        .format(OutputFormat::png())
+        .build();
+    let layout = GridLayout::default().fit(FitMode::ContainBlur);
+    let output = merge_with_layout(&[&f1, &f2], &layout, &options).unwrap();
+    assert_eq!(output.crops[0], Some(Rect::new(0, 0, 1080, 2340)));
+    // 留白处是模糊的图片而不是背景色
+    let im = decode(&output.bytes);
+    assert_ne!(im.get_pixel(5, 450).0, [255, 0, 255]);
+
+    let mut file = File::create("output-fit-contain-blur.png").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
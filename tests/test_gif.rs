@@ -1,8 +1,13 @@
+#[cfg(feature = "opencv")]
 use opencv::core::{Mat, MatTraitConstManual};
+#[cfg(feature = "opencv")]
 use opencv::videoio;
+#[cfg(feature = "opencv")]
 use opencv::videoio::VideoCaptureTrait;
+#[cfg(feature = "opencv")]
 use opencv::Result;
 
+#[cfg(feature = "opencv")]
 #[test]
 fn test_gif_from_file() -> Result<()> {
     pretty_env_logger::try_init().ok();
@@ -15,3 +20,18 @@ fn test_gif_from_file() -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_merge_gif_in_memory() {
+    pretty_env_logger::try_init().ok();
+    let gif = std::fs::read("./test-data/A.gif").unwrap();
+    let png = std::fs::read("./test-data/1.png").unwrap();
+    let output = merge_images::merge_with(&[&gif, &png], &Default::default()).unwrap();
+    // 第一帧画在第一个格子里
+    assert_eq!(output.crops[0], Some(merge_images::Rect::new(0, 0, 82, 82)));
+
+    // 截断的 GIF 报告出错的下标
+    let truncated = &gif[..20];
+    let err = merge_images::merge_with(&[&png, &truncated.to_vec()], &Default::default());
+    assert_eq!(err.unwrap_err().index(), Some(1));
+}
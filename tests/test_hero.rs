@@ -0,0 +1,155 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{
+    hero_with, merge_with_layout, FailurePolicy, HeroLayout, HeroPosition, ImageMeta, Layout,
+    MergeError, MergeOptions, Placement, Rect,
+};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn metas(n: usize) -> Vec<ImageMeta> {
+    (0..n)
+        .map(|index| ImageMeta {
+            index,
+            size: Some((100, 100)),
+        })
+        .collect()
+}
+
+/// 格子都在画布内、互不重叠，且主图的面积最大
+fn check(placement: &Placement, hero: usize) {
+    let (width, height) = placement.canvas;
+    let canvas = Rect::new(0, 0, width, height);
+    let area = |rect: Rect| rect.width * rect.height;
+    for (i, cell) in placement.cells.iter().enumerate() {
+        assert_eq!(cell.rect & canvas, cell.rect);
+        for other in &placement.cells[..i] {
+            assert!((cell.rect & other.rect).is_empty());
+        }
+        if i != hero {
+            assert!(area(cell.rect) < area(placement.cells[hero].rect));
+        }
+    }
+}
+
+#[test]
+fn test_hero_top() {
+    let options = MergeOptions::builder().padding(10).build();
+    let placement = HeroLayout::new(2, HeroPosition::Top)
+        .layout(&metas(3), &options)
+        .unwrap();
+    assert_eq!(placement.canvas, (1800, 1200 + 10 + 593));
+    let rects: Vec<Rect> = placement.cells.iter().map(|cell| cell.rect).collect();
+    assert_eq!(
+        rects,
+        vec![
+            Rect::new(0, 1210, 895, 593),
+            Rect::new(905, 1210, 895, 593),
+            Rect::new(0, 0, 1800, 1200),
+        ]
+    );
+}
+
+#[test]
+fn test_hero_any_count() {
+    for position in [HeroPosition::Top, HeroPosition::Left, HeroPosition::Center] {
+        for n in 1..=30 {
+            for hero in [0, n / 2, n - 1] {
+                let placement = HeroLayout::new(hero, position)
+                    .layout(&metas(n), &MergeOptions::default())
+                    .unwrap();
+                assert_eq!(placement.cells.len(), n);
+                check(&placement, hero);
+                match position {
+                    HeroPosition::Top => assert_eq!(placement.cells[hero].rect.y, 0),
+                    HeroPosition::Left => assert_eq!(placement.cells[hero].rect.x, 0),
+                    HeroPosition::Center if n >= 3 => {
+                        let rect = placement.cells[hero].rect;
+                        assert!(rect.y > 0 && rect.y + rect.height < placement.canvas.1);
+                    }
+                    HeroPosition::Center => {}
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_hero_invalid() {
+    let e = HeroLayout::new(3, HeroPosition::Top)
+        .layout(&metas(3), &MergeOptions::default())
+        .unwrap_err();
+    assert!(matches!(e, MergeError::Layout(_)));
+    let e = HeroLayout::new(0, HeroPosition::Top)
+        .ratio(0.5)
+        .layout(&metas(3), &MergeOptions::default())
+        .unwrap_err();
+    assert!(matches!(e, MergeError::Layout(_)));
+}
+
+#[test]
+fn test_merge_hero() {
+    pretty_env_logger::try_init().ok();
+    let images = ["1.png", "2.png", "3.png", "4.jpg", "5.png", "6.png"].map(data);
+
+    let output = hero_with(&images, 3, HeroPosition::Center, &MergeOptions::default()).unwrap();
+    let img = image::load_from_memory(&output.bytes).unwrap();
+    assert_eq!(img.width(), 1800);
+    let mut file = File::create("output-hero-center.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+
+    let layout = HeroLayout::new(0, HeroPosition::Left).size(900);
+    let output = merge_with_layout(&images, &layout, &MergeOptions::default()).unwrap();
+    let img = image::load_from_memory(&output.bytes).unwrap();
+    assert_eq!(img.height(), 900);
+    let mut file = File::create("output-hero-left.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+#[test]
+fn test_hero_with_skipped_images() {
+    pretty_env_logger::try_init().ok();
+    let options = MergeOptions::builder()
+        .on_failure(FailurePolicy::Skip)
+        .build();
+    let images = vec![
+        b"not an image".to_vec(),
+        data("1.png"),
+        data("2.png"),
+        data("3.png"),
+    ];
+
+    // 跳过第 0 张后主图仍然是第 2 张
+    let layout = HeroLayout::new(2, HeroPosition::Top);
+    let output = merge_with_layout(&images, &layout, &options).unwrap();
+    assert_eq!(output.skipped, vec![0]);
+    assert_eq!(output.cells[0], None);
+    let hero = output.cells[2].unwrap();
+    assert_eq!((hero.x, hero.y, hero.width), (0, 0, 1800));
+    for i in [1, 3] {
+        let cell = output.cells[i].unwrap();
+        assert!(cell.width * cell.height < hero.width * hero.height);
+    }
+}
+
+#[test]
+fn test_hero_skipped_hero() {
+    pretty_env_logger::try_init().ok();
+    let options = MergeOptions::builder()
+        .on_failure(FailurePolicy::Skip)
+        .build();
+    let images = vec![data("1.png"), b"not an image".to_vec(), data("2.png")];
+
+    // 主图本身被跳过时整个拼图失败，不会让其他图片成为主图
+    for position in [HeroPosition::Top, HeroPosition::Left, HeroPosition::Center] {
+        let layout = HeroLayout::new(1, position);
+        let e = merge_with_layout(&images, &layout, &options).unwrap_err();
+        assert!(matches!(e, MergeError::Layout(_)), "{:?}", e);
+    }
+}
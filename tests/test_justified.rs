@@ -0,0 +1,106 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{justified, ImageMeta, JustifiedLayout, LastRow, Layout, MergeOptions};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn metas(sizes: &[(i32, i32)]) -> Vec<ImageMeta> {
+    sizes
+        .iter()
+        .enumerate()
+        .map(|(index, &size)| ImageMeta {
+            index,
+            size: Some(size),
+        })
+        .collect()
+}
+
+#[test]
+fn test_justified_rows_fill_width() {
+    let images = metas(&[
+        (400, 300),
+        (300, 400),
+        (1000, 500),
+        (500, 500),
+        (800, 600),
+        (300, 900),
+        (1200, 400),
+        (400, 400),
+        (300, 200),
+    ]);
+    let options = MergeOptions::default();
+    let placement = JustifiedLayout::new(1000, 250)
+        .layout(&images, &options)
+        .unwrap();
+    assert_eq!(placement.canvas.0, 1000);
+    assert_eq!(placement.cells.len(), images.len());
+
+    // 除最后一行外，每一行都恰好填满宽度
+    let last_y = placement.cells.last().unwrap().rect.y;
+    for cell in &placement.cells {
+        let rect = cell.rect;
+        let row: Vec<_> = placement
+            .cells
+            .iter()
+            .filter(|other| other.rect.y == rect.y)
+            .collect();
+        assert!(row.iter().all(|other| other.rect.height == rect.height));
+        if rect.y != last_y {
+            let right = row.iter().map(|c| c.rect.x + c.rect.width).max().unwrap();
+            assert_eq!(right, 1000);
+        }
+    }
+    // 最后一行保持目标行高
+    assert_eq!(placement.cells.last().unwrap().rect.height, 250);
+}
+
+#[test]
+fn test_justified_last_row_justify() {
+    // 两张图片在目标行高下排不满一行
+    let images = metas(&[(1000, 500), (400, 400)]);
+    let placement = JustifiedLayout::new(2000, 400)
+        .last_row(LastRow::Justify)
+        .layout(&images, &MergeOptions::default())
+        .unwrap();
+    let last = placement.cells.last().unwrap().rect;
+    assert!(last.height > 400);
+    assert_eq!(last.x + last.width, 2000);
+    assert_eq!(last.y + last.height, placement.canvas.1);
+}
+
+#[test]
+fn test_justified_last_row_stays_inside_canvas() {
+    // 每张图片的宽度都是 400.5，逐张取整会比画布宽出 2 个像素
+    let images = metas(&[(801, 800); 4]);
+    for last_row in [LastRow::Left, LastRow::Center] {
+        let placement = JustifiedLayout::new(1633, 400)
+            .last_row(last_row)
+            .layout(&images, &MergeOptions::default())
+            .unwrap();
+        assert_eq!(placement.cells.len(), 4);
+        let last = placement.cells.last().unwrap().rect;
+        assert!(last.x + last.width <= placement.canvas.0);
+    }
+}
+
+#[test]
+fn test_merge_justified() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("2.png");
+    let f3 = data("3.png");
+    let f4 = data("4.jpg");
+    let f5 = data("5.png");
+    let f8 = data("8.jpg");
+    let f9 = data("9.jpg");
+    let out_im = justified(&[f1, f2, f3, f4, f5, f8, f9]).unwrap();
+
+    let mut output = File::create("output-justified.jpg").unwrap();
+    output.write_all(&out_im).unwrap();
+}
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{
+    merge_with_layout, Cell, FitMode, ImageMeta, Layout, MergeOptions, Placement, Rect,
+    WaterfallLayout,
+};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+/// 所有图片排成一行，高度相同
+struct Strip {
+    height: i32,
+}
+
+impl Layout for Strip {
+    fn layout(
+        &self,
+        images: &[ImageMeta],
+        options: &MergeOptions,
+    ) -> merge_images::Result<Placement> {
+        let mut x = 0;
+        let mut cells = vec![];
+        for image in images {
+            let (w, h) = image.size.unwrap();
+            let width = self.height * w / h;
+            cells.push(Cell {
+                rect: Rect::new(x, 0, width, self.height),
+                fit: FitMode::Stretch,
+            });
+            x += width + options.padding;
+        }
+        Ok(Placement {
+            canvas: (x - options.padding, self.height),
+            cells,
+        })
+    }
+}
+
+#[test]
+fn test_custom_layout() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("4.jpg");
+    let f3 = data("8.jpg");
+    let out_im = merge_with_layout(
+        &[f1, f2, f3],
+        &Strip { height: 300 },
+        &MergeOptions::default(),
+    )
+    .unwrap()
+    .bytes;
+    let size = imagesize::blob_size(&out_im).unwrap();
+    assert_eq!(size.height, 300);
+
+    let mut output = File::create("output-layout-strip.jpg").unwrap();
+    output.write_all(&out_im).unwrap();
+}
+
+/// 返回的格子超出画布时应当报错
+struct Broken;
+
+impl Layout for Broken {
+    fn layout(&self, images: &[ImageMeta], _: &MergeOptions) -> merge_images::Result<Placement> {
+        let cells = images
+            .iter()
+            .map(|_| Cell {
+                rect: Rect::new(50, 50, 100, 100),
+                fit: FitMode::Cover,
+            })
+            .collect();
+        Ok(Placement {
+            canvas: (100, 100),
+            cells,
+        })
+    }
+}
+
+#[test]
+fn test_layout_out_of_canvas() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("4.jpg");
+    let f2 = data("8.jpg");
+    assert!(merge_with_layout(&[f1, f2], &Broken, &MergeOptions::default()).is_err());
+}
+
+#[test]
+fn test_waterfall_very_wide_image() {
+    // 800 * 1 / 10000 取整为 0，格子至少保留 1 像素高
+    let images = [(10000, 1), (100, 100)]
+        .iter()
+        .enumerate()
+        .map(|(index, &size)| ImageMeta {
+            index,
+            size: Some(size),
+        })
+        .collect::<Vec<_>>();
+    let placement = WaterfallLayout::default()
+        .layout(&images, &MergeOptions::default())
+        .unwrap();
+    assert_eq!(placement.cells[0].rect, Rect::new(0, 0, 800, 1));
+    assert_eq!(placement.cells[1].rect, Rect::new(810, 0, 800, 800));
+}
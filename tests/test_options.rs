@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{
+    merge_with, waterfall_with, ChromaSubsampling, GridLayout, ImageMeta, Layout, MergeError,
+    MergeOptions, OutputFormat, WaterfallLayout,
+};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_merge_with_png() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("2.png");
+    let f3 = data("3.png");
+    let f4 = data("4.jpg");
+    let options = MergeOptions::builder()
+        .padding(30)
+        .background([0, 0, 0])
+        .format(OutputFormat::png())
+        .build();
+    let out_im = merge_with(&[f1, f2, f3, f4], &options).unwrap().bytes;
+    assert_eq!(&out_im[..4], b"\x89PNG");
+
+    let mut output = File::create("output-options-4.png").unwrap();
+    output.write_all(&out_im).unwrap();
+}
+
+#[test]
+fn test_waterfall_with_columns() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("2.png");
+    let f3 = data("3.png");
+    let f4 = data("4.jpg");
+    let options = MergeOptions::builder().columns(4).cell_size(200).build();
+    let out_im = waterfall_with(&[f1, f2, f3, f4], &options).unwrap().bytes;
+    let size = imagesize::blob_size(&out_im).unwrap();
+    assert_eq!(size.width, 4 * 200 + 3 * 10);
+
+    let mut output = File::create("output-options-waterfall.jpg").unwrap();
+    output.write_all(&out_im).unwrap();
+}
+
+#[test]
+fn test_invalid_padding_columns_and_cell_size() {
+    let metas: Vec<_> = (0..12)
+        .map(|index| ImageMeta {
+            index,
+            size: Some((100, 100)),
+        })
+        .collect();
+    let layouts: [&dyn Layout; 2] = [&GridLayout::default(), &WaterfallLayout::default()];
+    for layout in layouts {
+        let options = MergeOptions::builder().padding(-1).build();
+        let e = layout.layout(&metas, &options).unwrap_err();
+        assert!(matches!(e, MergeError::Layout(_)), "{:?}", e);
+
+        for value in [0, -1] {
+            let options = MergeOptions::builder().columns(value).build();
+            let e = layout.layout(&metas, &options).unwrap_err();
+            assert!(matches!(e, MergeError::Layout(_)), "{:?}", e);
+
+            let options = MergeOptions::builder().cell_size(value).build();
+            let e = layout.layout(&metas, &options).unwrap_err();
+            assert!(matches!(e, MergeError::Layout(_)), "{:?}", e);
+        }
+    }
+}
+
+#[test]
+fn test_merge_with_formats() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("4.jpg");
+    let formats = [
+        (
+            OutputFormat::Jpeg {
+                quality: 80,
+                progressive: true,
+                chroma_subsampling: Some(ChromaSubsampling::Yuv444),
+            },
+            &b"\xff\xd8"[..],
+        ),
+        (OutputFormat::Png { compression: 9 }, &b"\x89PNG"[..]),
+        (OutputFormat::webp(80), &b"RIFF"[..]),
+        (OutputFormat::webp_lossless(), &b"RIFF"[..]),
+        (OutputFormat::Bmp, &b"BM"[..]),
+    ];
+    for (format, magic) in formats.iter() {
+        let options = MergeOptions::builder().format(*format).build();
+        let out_im = merge_with(&[&f1, &f2], &options).unwrap().bytes;
+        assert!(out_im.starts_with(magic), "{:?}", format);
+    }
+}
+
+#[test]
+fn test_merge_with_max_bytes() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("2.png");
+    let f3 = data("3.png");
+    let f4 = data("4.jpg");
+    let images = [&f1, &f2, &f3, &f4];
+
+    let full = merge_with(&images, &MergeOptions::default()).unwrap();
+    assert_eq!(full.quality, Some(95));
+    assert_eq!(full.scale, 1.);
+
+    // 降低编码质量就能满足
+    let max_bytes = full.bytes.len() / 2;
+    let options = MergeOptions::builder().max_bytes(max_bytes).build();
+    let output = merge_with(&images, &options).unwrap();
+    assert!(output.bytes.len() <= max_bytes);
+    assert!(output.quality.unwrap() < 95);
+
+    // 只能缩小画布
+    let options = MergeOptions::builder()
+        .format(OutputFormat::png())
+        .max_bytes(100_000)
+        .build();
+    let output = merge_with(&images, &options).unwrap();
+    assert!(output.bytes.len() <= 100_000);
+    assert!(output.scale < 1.);
+    assert_eq!(output.quality, None);
+
+    let mut file = File::create("output-options-max-bytes.png").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
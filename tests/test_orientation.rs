@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::io::*;
+
+use image::imageops;
+use merge_images::{merge_with, waterfall_with, MergeOptions, OutputFormat, Rect};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_waterfall_rotated() {
+    pretty_env_logger::try_init().ok();
+    // 4.jpg 是 300x168 的横图，Orientation = 6 时应该显示为竖图
+    let f1 = data("4-orientation-6.jpg");
+    let f2 = data("4.jpg");
+    let output = waterfall_with(&[f1, f2], &MergeOptions::default()).unwrap();
+    assert_eq!(output.cells[0], Some(Rect::new(0, 0, 800, 800 * 300 / 168)));
+    assert_eq!(
+        output.cells[1],
+        Some(Rect::new(810, 0, 800, 800 * 168 / 300))
+    );
+    assert_eq!(output.crops[0], Some(Rect::new(0, 0, 168, 300)));
+
+    let mut file = File::create("output-orientation-waterfall.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+#[test]
+fn test_merge_rotated_180() {
+    pretty_env_logger::try_init().ok();
+    let options = MergeOptions::builder().format(OutputFormat::png()).build();
+    let rotated = merge_with(&[data("4-orientation-3.jpg"), data("4.jpg")], &options).unwrap();
+    let upright = merge_with(&[data("4.jpg"), data("4.jpg")], &options).unwrap();
+    assert_eq!(rotated.cells, upright.cells);
+
+    let rect = rotated.cells[0].unwrap();
+    let cell = |bytes: &[u8]| {
+        let im = image::load_from_memory(bytes).unwrap().to_rgb8();
+        let (x, y) = (rect.x as u32, rect.y as u32);
+        imageops::crop_imm(&im, x, y, rect.width as u32, rect.height as u32).to_image()
+    };
+    let (a, b) = (cell(&rotated.bytes), cell(&upright.bytes));
+    let flipped = imageops::rotate180(&b);
+    let diff: u64 = a
+        .as_raw()
+        .iter()
+        .zip(flipped.as_raw())
+        .map(|(x, y)| (*x as i32 - *y as i32).unsigned_abs() as u64)
+        .sum();
+    let mean = diff as f64 / a.as_raw().len() as f64;
+    assert!(mean < 5., "{}", mean);
+}
@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{merge_with, waterfall_with, MergeOptions, Rect};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_grid_output_layout() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("2.png");
+    let f3 = data("3.png");
+    let f4 = data("4.jpg");
+    let output = merge_with(&[&f1, &f2, &f3, &f4], &MergeOptions::default()).unwrap();
+
+    let size = imagesize::blob_size(&output.bytes).unwrap();
+    assert_eq!(output.canvas, (size.width as i32, size.height as i32));
+    assert!(output.skipped.is_empty());
+    assert_eq!(output.cells.len(), 4);
+    assert_eq!(output.cells[0], Some(Rect::new(0, 0, 900, 900)));
+
+    // 宫格会从原图中间裁剪出正方形
+    let source = imagesize::blob_size(&f1).unwrap();
+    let crop = output.crops[0].unwrap();
+    assert_eq!(
+        crop.width.min(crop.height),
+        source.width.min(source.height) as i32
+    );
+    assert!((crop.width - crop.height).abs() <= 1);
+
+    assert_eq!(output.index_at(450, 450), Some(0));
+    assert_eq!(output.index_at(1350, 1350), Some(3));
+    // 间距不属于任何图片
+    assert_eq!(output.index_at(905, 450), None);
+}
+
+#[test]
+fn test_waterfall_output_layout() {
+    pretty_env_logger::try_init().ok();
+    let f1 = data("1.png");
+    let f2 = data("8.jpg");
+    let f3 = data("9.jpg");
+    let output = waterfall_with(&[&f1, &f2, &f3], &MergeOptions::default()).unwrap();
+    for (cell, crop) in output.cells.iter().zip(&output.crops) {
+        let (cell, crop) = (cell.unwrap(), crop.unwrap());
+        assert_eq!(cell.width, 800);
+        // 瀑布流不裁剪
+        assert_eq!((crop.x, crop.y), (0, 0));
+    }
+}
+
+/// 没有 `webp` feature 的 image 后端只能无损编码 WebP，不报告没有用到的编码质量
+#[cfg(not(any(feature = "opencv", feature = "webp")))]
+#[test]
+fn test_webp_without_lossy_encoder() {
+    pretty_env_logger::try_init().ok();
+    let format = merge_images::OutputFormat::webp(80);
+    assert_eq!(format.quality(), None);
+    let options = MergeOptions::builder().format(format).build();
+    let output = merge_with(&[data("1.png"), data("4.jpg")], &options).unwrap();
+    assert!(output.bytes.starts_with(b"RIFF"));
+    assert_eq!(output.quality, None);
+}
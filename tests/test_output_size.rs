@@ -0,0 +1,112 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{
+    merge_with, GridLayout, HeroLayout, HeroPosition, ImageMeta, JustifiedLayout, Layout,
+    LayoutTemplate, MergeError, MergeOptions, OutputSize, Rect, WaterfallLayout,
+};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn metas(n: usize) -> Vec<ImageMeta> {
+    (0..n)
+        .map(|index| ImageMeta {
+            index,
+            size: Some((100, 100)),
+        })
+        .collect()
+}
+
+#[test]
+fn test_output_width() {
+    pretty_env_logger::try_init().ok();
+    let options = MergeOptions::builder()
+        .padding(10)
+        .output_size(OutputSize::Width(605))
+        .build();
+    let placement = GridLayout::default().layout(&metas(4), &options).unwrap();
+    // 原本是 1810 x 1810，缩小到 1/3 左右
+    assert_eq!(placement.canvas, (605, 605));
+    let rects: Vec<Rect> = placement.cells.iter().map(|cell| cell.rect).collect();
+    assert_eq!(
+        rects,
+        vec![
+            Rect::new(0, 0, 301, 301),
+            Rect::new(0, 304, 301, 301),
+            Rect::new(304, 0, 301, 301),
+            Rect::new(304, 304, 301, 301),
+        ]
+    );
+
+    let images = ["1.png", "2.png", "3.png", "4.jpg"].map(data);
+    let output = merge_with(&images, &options).unwrap();
+    let img = image::load_from_memory(&output.bytes).unwrap();
+    assert_eq!((img.width(), img.height()), (605, 605));
+
+    let mut file = File::create("output-output-width.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+}
+
+#[test]
+fn test_output_max_dimension() {
+    let options = MergeOptions::builder()
+        .output_size(OutputSize::MaxDimension(800))
+        .build();
+    let natural = GridLayout::default()
+        .layout(&metas(20), &MergeOptions::default())
+        .unwrap();
+    let placement = GridLayout::default().layout(&metas(20), &options).unwrap();
+    let (width, height) = placement.canvas;
+    assert_eq!(width.max(height), 800);
+    assert_eq!(placement.cells.len(), natural.cells.len());
+    for cell in &placement.cells {
+        let rect = cell.rect;
+        assert!(rect.x + rect.width <= width && rect.y + rect.height <= height);
+    }
+    // 相邻的格子不重叠
+    for (i, a) in placement.cells.iter().enumerate() {
+        for b in &placement.cells[..i] {
+            assert!((a.rect & b.rect).is_empty());
+        }
+    }
+
+    // 本来就比较小时不放大
+    let options = MergeOptions::builder()
+        .output_size(OutputSize::MaxDimension(100_000))
+        .build();
+    let placement = GridLayout::default().layout(&metas(20), &options).unwrap();
+    assert_eq!(placement.canvas, natural.canvas);
+}
+
+#[test]
+fn test_output_size_invalid() {
+    let options = MergeOptions::builder()
+        .output_size(OutputSize::Width(0))
+        .build();
+    let e = GridLayout::default()
+        .layout(&metas(3), &options)
+        .unwrap_err();
+    assert!(matches!(e, MergeError::Layout(_)));
+}
+
+#[test]
+fn test_output_size_grid_only() {
+    let options = MergeOptions::builder()
+        .output_size(OutputSize::Width(600))
+        .build();
+    let layouts: Vec<Box<dyn Layout>> = vec![
+        Box::new(WaterfallLayout::default()),
+        Box::new(JustifiedLayout::default()),
+        Box::new(HeroLayout::new(0, HeroPosition::Top)),
+        Box::new(LayoutTemplate::grid()),
+    ];
+    for layout in layouts {
+        let e = layout.layout(&metas(3), &options).unwrap_err();
+        assert!(matches!(e, MergeError::Layout(_)), "{:?}", e);
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::io::*;
+
+#[cfg(feature = "rayon")]
+use merge_images::MergeOutput;
+use merge_images::{merge_with, FailurePolicy, MergeOptions, OutputFormat};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn batch() -> Vec<Vec<u8>> {
+    let names = [
+        "1.png", "2.png", "3.png", "4.jpg", "5.png", "6.png", "7.png", "8.jpg", "9.jpg",
+    ];
+    names
+        .iter()
+        .cycle()
+        .take(20)
+        .map(|name| data(name))
+        .collect()
+}
+
+#[test]
+fn test_batch_output_is_stable() {
+    pretty_env_logger::try_init().ok();
+    let images = batch();
+    let options = MergeOptions::builder().format(OutputFormat::png()).build();
+    let first = merge_with(&images, &options).unwrap();
+    let second = merge_with(&images, &options).unwrap();
+    assert_eq!(first.bytes, second.bytes);
+    assert_eq!(first.cells, second.cells);
+    assert_eq!(first.crops, second.crops);
+    assert!(first.crops.iter().all(Option::is_some));
+
+    let mut file = File::create("output-parallel.png").unwrap();
+    file.write_all(&first.bytes).unwrap();
+}
+
+/// 在 threads 个线程的线程池中拼图；batch_size 跟随线程数，只有一个线程时逐张处理
+#[cfg(feature = "rayon")]
+fn merge_in_pool(threads: usize, images: &[Vec<u8>], options: &MergeOptions) -> MergeOutput {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap()
+        .install(|| merge_with(images, options))
+        .unwrap()
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_parallel_matches_sequential() {
+    pretty_env_logger::try_init().ok();
+    // 并行解码时画到画布上的顺序不变，输出与逐张处理一致
+    let images = batch();
+    let options = MergeOptions::builder().format(OutputFormat::png()).build();
+    let parallel = merge_in_pool(4, &images, &options);
+    let sequential = merge_in_pool(1, &images, &options);
+    assert_eq!(parallel.bytes, sequential.bytes);
+    assert_eq!(parallel.cells, sequential.cells);
+    assert_eq!(parallel.crops, sequential.crops);
+}
+
+#[test]
+fn test_batch_reports_first_failure() {
+    pretty_env_logger::try_init().ok();
+    let mut images = batch();
+    images[13] = b"not an image".to_vec();
+    images[7] = b"not an image either".to_vec();
+    let options = MergeOptions::builder()
+        .on_failure(FailurePolicy::Abort)
+        .build();
+    let err = merge_with(&images, &options).unwrap_err();
+    assert_eq!(err.index(), Some(7));
+
+    let options = MergeOptions::builder()
+        .on_failure(FailurePolicy::Placeholder)
+        .build();
+    let output = merge_with(&images, &options).unwrap();
+    assert_eq!(output.placeholders, vec![7, 13]);
+}
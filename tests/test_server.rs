@@ -0,0 +1,222 @@
+#![cfg(feature = "server")]
+
+use std::fs::File;
+use std::io::*;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use base64::Engine;
+use merge_images::server::{router, ServerConfig};
+use tower::ServiceExt;
+
+const BOUNDARY: &str = "merge-images-test-boundary";
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+/// 每个文件是一张图片，fields 是文本字段
+fn multipart(files: &[&str], fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut body = vec![];
+    for (i, name) in files.iter().enumerate() {
+        write!(
+            body,
+            "--{}\r\nContent-Disposition: form-data; name=\"image{}\"; filename=\"{}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            BOUNDARY, i, name
+        )
+        .unwrap();
+        body.extend_from_slice(&data(name));
+        body.extend_from_slice(b"\r\n");
+    }
+    for (name, value) in fields {
+        write!(
+            body,
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            BOUNDARY, name, value
+        )
+        .unwrap();
+    }
+    write!(body, "--{}--\r\n", BOUNDARY).unwrap();
+    body
+}
+
+fn multipart_request(body: Vec<u8>) -> Request<Body> {
+    Request::post("/merge")
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_server_health() {
+    let response = router(ServerConfig::default())
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_server_multipart() {
+    pretty_env_logger::try_init().ok();
+    let body = multipart(
+        &["1.png", "2.png", "3.png"],
+        &[("layout", "waterfall"), ("format", "png"), ("padding", "0")],
+    );
+    let response = router(ServerConfig::default())
+        .oneshot(multipart_request(body))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).unwrap();
+
+    let mut file = File::create("output-server.png").unwrap();
+    file.write_all(&bytes).unwrap();
+}
+
+#[tokio::test]
+async fn test_server_json() {
+    pretty_env_logger::try_init().ok();
+    let images: Vec<_> = ["1.png", "4.jpg"]
+        .iter()
+        .map(|name| base64::engine::general_purpose::STANDARD.encode(data(name)))
+        .collect();
+    let body = serde_json::json!({ "images": images, "quality": 80, "background": "#000000" });
+    let request = Request::post("/merge")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = router(ServerConfig::default())
+        .oneshot(request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+}
+
+#[tokio::test]
+async fn test_server_rejects_bad_requests() {
+    let config = ServerConfig {
+        max_body_bytes: 1 << 20,
+        max_images: 2,
+        ..Default::default()
+    };
+
+    // 未知参数
+    let body = multipart(&["1.png", "2.png"], &[("no_such_option", "1")]);
+    let response = router(config.clone())
+        .oneshot(multipart_request(body))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // 图片太多
+    let body = multipart(&["1.png", "2.png", "3.png"], &[]);
+    let response = router(config.clone())
+        .oneshot(multipart_request(body))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // 请求体太大
+    let body = vec![0; 2 << 20];
+    let response = router(config.clone())
+        .oneshot(multipart_request(body))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // 不是图片
+    let images = [
+        base64::engine::general_purpose::STANDARD.encode(b"not an image"),
+        base64::engine::general_purpose::STANDARD.encode(data("1.png")),
+    ];
+    let body = serde_json::json!({ "images": images });
+    let request = Request::post("/merge")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = router(config).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_server_limits_canvas() {
+    // 尺寸参数超出范围
+    let body = multipart(&["1.png", "2.png"], &[("cell_size", "1000000")]);
+    let response = router(ServerConfig::default())
+        .oneshot(multipart_request(body))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // 参数都在范围内，但画布超过了上限：瀑布流 2 列 x 10000 像素宽
+    let config = ServerConfig {
+        max_canvas_pixels: 10_000_000,
+        ..Default::default()
+    };
+    let body = multipart(
+        &["1.png", "2.png"],
+        &[("layout", "waterfall"), ("cell_size", "10000")],
+    );
+    let response = router(config)
+        .oneshot(multipart_request(body))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("canvas"));
+}
+
+#[tokio::test]
+async fn test_server_concurrency_limit() {
+    // 并发上限为 0 时所有拼图请求都被拒绝
+    let config = ServerConfig {
+        max_concurrency: 0,
+        ..Default::default()
+    };
+    let body = multipart(&["1.png", "2.png"], &[]);
+    let response = router(config)
+        .oneshot(multipart_request(body))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_server_permit_outlives_disconnect() {
+    pretty_env_logger::try_init().ok();
+    let config = ServerConfig {
+        max_concurrency: 1,
+        ..Default::default()
+    };
+    let app = router(config);
+    let names: Vec<_> = ["1.png", "2.png", "3.png", "6.png", "7.png"]
+        .iter()
+        .cycle()
+        .take(30)
+        .copied()
+        .collect();
+
+    // 客户端在拼图开始后断开，future 被丢弃
+    let request = app
+        .clone()
+        .oneshot(multipart_request(multipart(&names, &[])));
+    let result = tokio::time::timeout(std::time::Duration::from_millis(200), request).await;
+    assert!(result.is_err(), "should still be merging");
+
+    // 阻塞线程池中的拼图还没有停止，仍然占用唯一的许可
+    let body = multipart(&["1.png", "2.png"], &[]);
+    let response = app.oneshot(multipart_request(body)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::*;
+
+use merge_images::{
+    merge_with, merge_with_layout, FitMode, ImageMeta, Layout, LayoutTemplate, MergeError,
+    MergeOptions, Rect, TemplateCell, TemplateVariant,
+};
+
+fn data(name: &str) -> Vec<u8> {
+    let mut f = File::open(format!("./test-data/{}", name)).unwrap();
+    let mut buf = vec![];
+    f.read_to_end(&mut buf).unwrap();
+    buf
+}
+
+fn metas(n: usize) -> Vec<ImageMeta> {
+    (0..n)
+        .map(|index| ImageMeta {
+            index,
+            size: Some((100, 100)),
+        })
+        .collect()
+}
+
+#[test]
+fn test_grid_template() {
+    let options = MergeOptions::builder().padding(10).build();
+    let placement = LayoutTemplate::grid().layout(&metas(5), &options).unwrap();
+    assert_eq!(placement.canvas, (1820, 1510));
+    let rects: Vec<Rect> = placement.cells.iter().map(|cell| cell.rect).collect();
+    assert_eq!(
+        rects,
+        vec![
+            Rect::new(0, 0, 900, 900),
+            Rect::new(910, 0, 900, 900),
+            Rect::new(0, 910, 600, 600),
+            Rect::new(610, 910, 600, 600),
+            Rect::new(1220, 910, 600, 600),
+        ]
+    );
+
+    // 宫格使用同一个模板，输出相同
+    let images: Vec<_> = ["1.png", "2.png", "3.png", "4.jpg", "5.png"]
+        .iter()
+        .map(|name| data(name))
+        .collect();
+    let output = merge_with(&images, &options).unwrap();
+    let templated = merge_with_layout(&images, &LayoutTemplate::grid(), &options).unwrap();
+    assert_eq!(output.bytes, templated.bytes);
+}
+
+#[test]
+fn test_template_validation() {
+    // 重叠
+    let variant = TemplateVariant::new(
+        (4, 2),
+        100,
+        vec![TemplateCell::new(0, 0, 2, 2), TemplateCell::new(1, 0, 2, 2)],
+    );
+    let err = LayoutTemplate::new(vec![variant]).unwrap_err();
+    assert!(matches!(err, MergeError::Layout(_)), "{}", err);
+
+    // 超出画布
+    let variant = TemplateVariant::new(
+        (4, 2),
+        100,
+        vec![TemplateCell::new(0, 0, 2, 2), TemplateCell::new(3, 0, 2, 2)],
+    );
+    assert!(LayoutTemplate::new(vec![variant]).is_err());
+
+    // 格子的右边溢出 i32
+    let variant = TemplateVariant::new((4, 2), 100, vec![TemplateCell::new(i32::MAX, 0, 1, 1)]);
+    let err = LayoutTemplate::new(vec![variant]).unwrap_err();
+    assert!(matches!(err, MergeError::Layout(_)), "{}", err);
+
+    // 像素大小溢出 i32
+    let variant = TemplateVariant::new((4, 2), i32::MAX / 2, vec![TemplateCell::new(0, 0, 1, 1)]);
+    assert!(LayoutTemplate::new(vec![variant]).is_err());
+    let variant = TemplateVariant::new(
+        (4, 2),
+        100,
+        vec![TemplateCell::new(0, 0, 2, 2), TemplateCell::new(2, 0, 2, 2)],
+    );
+    let template = LayoutTemplate::new(vec![variant]).unwrap();
+    let options = MergeOptions::builder().padding(i32::MAX).build();
+    let err = template.layout(&metas(2), &options).unwrap_err();
+    assert!(matches!(err, MergeError::Layout(_)), "{}", err);
+
+    // 同一个数量有两个变体
+    let variant = TemplateVariant::new(
+        (4, 2),
+        100,
+        vec![TemplateCell::new(0, 0, 2, 2), TemplateCell::new(2, 0, 2, 2)],
+    );
+    assert!(LayoutTemplate::new(vec![variant.clone(), variant.clone()]).is_err());
+
+    // 没有对应数量的变体
+    let template = LayoutTemplate::new(vec![variant]).unwrap();
+    let images = vec![data("1.png"), data("2.png"), data("3.png")];
+    let err = merge_with_layout(&images, &template, &MergeOptions::default()).unwrap_err();
+    assert!(matches!(err, MergeError::Layout(_)), "{}", err);
+}
+
+#[test]
+fn test_template_gap_and_fit() {
+    // 左边一张大图，右边上下两张小图
+    let mut variant = TemplateVariant::new(
+        (3, 2),
+        200,
+        vec![
+            TemplateCell::new(0, 0, 2, 2),
+            TemplateCell::new(2, 0, 1, 1),
+            TemplateCell {
+                fit: Some(FitMode::Contain),
+                ..TemplateCell::new(2, 1, 1, 1)
+            },
+        ],
+    );
+    variant.gap = Some(20);
+    let template = LayoutTemplate::new(vec![variant]).unwrap();
+    let placement = template
+        .layout(&metas(3), &MergeOptions::default())
+        .unwrap();
+    assert_eq!(placement.canvas, (620, 420));
+    assert_eq!(placement.cells[1].rect, Rect::new(420, 0, 200, 200));
+    assert_eq!(placement.cells[2].rect, Rect::new(420, 220, 200, 200));
+    assert_eq!(placement.cells[0].fit, FitMode::Cover);
+    assert_eq!(placement.cells[2].fit, FitMode::Contain);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn test_template_from_toml() {
+    pretty_env_logger::try_init().ok();
+    let template = LayoutTemplate::from_toml(
+        r#"
+        [[variants]]
+        canvas = [3, 2]
+        unit = 300
+        gap = 0
+        cells = [
+            { x = 0, y = 0, width = 2, height = 2 },
+            { x = 2, y = 0, width = 1, height = 1 },
+            { x = 2, y = 1, width = 1, height = 1, fit = "contain_blur" },
+        ]
+        "#,
+    )
+    .unwrap();
+    let images = vec![data("1.png"), data("2.png"), data("3.png")];
+    let output = merge_with_layout(&images, &template, &MergeOptions::default()).unwrap();
+    assert_eq!(output.canvas, (900, 600));
+    assert_eq!(output.cells[2], Some(Rect::new(600, 300, 300, 300)));
+
+    let mut file = File::create("output-template.jpg").unwrap();
+    file.write_all(&output.bytes).unwrap();
+
+    let err = LayoutTemplate::from_toml("[[variants]]\ncanvas = [1, 1]\nunit = 1\ncells = []")
+        .unwrap_err();
+    assert!(matches!(err, MergeError::Layout(_)), "{}", err);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_template_from_json() {
+    let json = serde_json::to_string(&LayoutTemplate::grid()).unwrap();
+    assert_eq!(
+        LayoutTemplate::from_json(&json).unwrap(),
+        LayoutTemplate::grid()
+    );
+
+    let err = LayoutTemplate::from_json(r#"{"variants": [{"canvas": [1, 1]}]}"#).unwrap_err();
+    assert!(matches!(err, MergeError::Layout(_)), "{}", err);
+}